@@ -0,0 +1,14 @@
+use crate::Server;
+
+/// Returns the server run by the `wslpluginapi-agent` binary.
+///
+/// It answers the following methods:
+/// - `ping`: returns its payload unchanged.
+/// - `version`: returns the version of the agent as UTF-8.
+pub fn agent_server() -> Server {
+    Server::new()
+        .method("ping", |payload| Ok(payload.to_vec()))
+        .method("version", |_| {
+            Ok(env!("CARGO_PKG_VERSION").as_bytes().to_vec())
+        })
+}
@@ -0,0 +1,277 @@
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::str::FromStr;
+
+/// A `major.minor.revision` version.
+///
+/// Same layout as `WSLVersion` of `wslpluginapi-sys`, which converts from and to it. The agent
+/// runs in the distribution and cannot depend on `wslpluginapi-sys`, whose bindings only build
+/// for Windows targets, hence its own type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WslVersion {
+    pub major: u32,
+    pub minor: u32,
+    pub revision: u32,
+}
+
+impl WslVersion {
+    pub const fn new(major: u32, minor: u32, revision: u32) -> Self {
+        Self {
+            major,
+            minor,
+            revision,
+        }
+    }
+}
+
+impl fmt::Display for WslVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.revision)
+    }
+}
+
+/// Error returned when a version does not start with `major.minor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseWslVersionError(String);
+
+impl fmt::Display for ParseWslVersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid version {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseWslVersionError {}
+
+impl FromStr for WslVersion {
+    type Err = ParseWslVersionError;
+
+    /// Parses the leading numbers of a version, ignoring what follows them.
+    ///
+    /// `5.15.167.4-microsoft-standard-WSL2` gives `5.15.167`, and a missing revision is 0.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseWslVersionError(s.to_owned());
+        let numeric = s
+            .trim()
+            .split(|c: char| !c.is_ascii_digit() && c != '.')
+            .next()
+            .unwrap_or_default();
+        let mut components = numeric
+            .split('.')
+            .map(|component| component.parse::<u32>().map_err(|_| error()));
+        let major = components.next().ok_or_else(error)??;
+        let minor = components.next().ok_or_else(error)??;
+        let revision = components.next().transpose()?.unwrap_or(0);
+        Ok(Self::new(major, minor, revision))
+    }
+}
+
+/// Generation of WSL running the current distribution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WslGeneration {
+    /// The system call translation layer, with a kernel release such as `4.4.0-19041-Microsoft`.
+    Wsl1,
+    /// The lightweight VM, with a kernel release such as `5.15.167.4-microsoft-standard-WSL2`.
+    Wsl2,
+}
+
+/// What could be learned about the WSL environment of the current process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WslEnvironment {
+    /// `None` when only the environment variables or interop markers reveal WSL.
+    pub generation: Option<WslGeneration>,
+    pub kernel_release: Option<String>,
+    /// Version of the Linux kernel; WSL1 reports a fixed `4.4.0`.
+    pub kernel_version: Option<WslVersion>,
+    /// Version of the WSL package, from `wslinfo --wsl-version`; `None` before WSL 2.2.
+    pub wsl_version: Option<WslVersion>,
+    /// Value of `WSL_DISTRO_NAME`.
+    pub distribution_name: Option<String>,
+    /// Whether Windows executables can be started, through `WSL_INTEROP` or the binfmt entry.
+    pub interop: bool,
+}
+
+/// Parses the output of `wslinfo --wsl-version`, which is the version of the WSL package.
+pub fn parse_wslinfo_version(output: &str) -> Option<WslVersion> {
+    output.lines().find_map(|line| line.trim().parse().ok())
+}
+
+/// Runs `wslinfo --wsl-version`, available in distributions started by WSL 2.2 or later.
+pub fn wsl_package_version() -> io::Result<Option<WslVersion>> {
+    let output = Command::new("wslinfo").arg("--wsl-version").output()?;
+    if !output.status.success() {
+        return Ok(None);
+    }
+    Ok(parse_wslinfo_version(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Detects WSL from the files under a root directory and from environment variables.
+///
+/// The root is `/` on a live system; tests point it at a fixture tree.
+#[derive(Debug, Clone)]
+pub struct WslDetector<E = fn(&str) -> Option<String>> {
+    root: PathBuf,
+    env: E,
+    wslinfo: fn() -> io::Result<Option<WslVersion>>,
+}
+
+impl Default for WslDetector {
+    fn default() -> Self {
+        Self::new("/", |name| std::env::var(name).ok())
+    }
+}
+
+impl<E: Fn(&str) -> Option<String>> WslDetector<E> {
+    pub fn new(root: impl Into<PathBuf>, env: E) -> Self {
+        Self {
+            root: root.into(),
+            env,
+            wslinfo: wsl_package_version,
+        }
+    }
+
+    /// Replaces [`wsl_package_version`] as the source of the WSL package version.
+    pub fn with_wslinfo(mut self, wslinfo: fn() -> io::Result<Option<WslVersion>>) -> Self {
+        self.wslinfo = wslinfo;
+        self
+    }
+
+    fn read(&self, path: &str) -> Option<String> {
+        fs::read_to_string(self.root.join(path))
+            .ok()
+            .map(|content| content.trim().to_owned())
+            .filter(|content| !content.is_empty())
+    }
+
+    fn exists(&self, path: &str) -> bool {
+        Path::new(&self.root).join(path).exists()
+    }
+
+    /// Returns the WSL environment, or `None` when not running under WSL.
+    pub fn detect(&self) -> Option<WslEnvironment> {
+        let proc_version = self.read("proc/version");
+        let kernel_release = self.read("proc/sys/kernel/osrelease").or_else(|| {
+            proc_version
+                .as_deref()?
+                .strip_prefix("Linux version ")?
+                .split_ascii_whitespace()
+                .next()
+                .map(str::to_owned)
+        });
+        let generation = kernel_release
+            .as_deref()
+            .or(proc_version.as_deref())
+            .and_then(|release| {
+                if release.contains("WSL2") || release.contains("microsoft-standard") {
+                    Some(WslGeneration::Wsl2)
+                } else if release.contains("Microsoft") {
+                    Some(WslGeneration::Wsl1)
+                } else {
+                    None
+                }
+            });
+        let distribution_name = (self.env)("WSL_DISTRO_NAME").filter(|name| !name.is_empty());
+        let interop = (self.env)("WSL_INTEROP").is_some()
+            || self.exists("proc/sys/fs/binfmt_misc/WSLInterop")
+            || self.exists("run/WSL");
+        if generation.is_none() && distribution_name.is_none() && !interop {
+            return None;
+        }
+        Some(WslEnvironment {
+            generation,
+            kernel_version: kernel_release.as_deref().and_then(|r| r.parse().ok()),
+            kernel_release,
+            // Only asked once WSL is detected, `wslinfo` does not exist elsewhere.
+            wsl_version: (self.wslinfo)().ok().flatten(),
+            distribution_name,
+            interop,
+        })
+    }
+}
+
+/// Detects WSL for the current process.
+pub fn detect_wsl() -> Option<WslEnvironment> {
+    WslDetector::default().detect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/detect")
+            .join(name)
+    }
+
+    #[test]
+    fn test_version_parsing() {
+        assert_eq!(
+            "5.15.167.4-microsoft-standard-WSL2".parse(),
+            Ok(WslVersion::new(5, 15, 167))
+        );
+        assert_eq!("2.4\n".parse(), Ok(WslVersion::new(2, 4, 0)));
+        assert!("WSL".parse::<WslVersion>().is_err());
+        assert_eq!(
+            parse_wslinfo_version("2.4.4\n"),
+            Some(WslVersion::new(2, 4, 4))
+        );
+        assert!(WslVersion::new(2, 4, 4) > WslVersion::new(2, 1, 12));
+    }
+
+    #[test]
+    fn test_detect_wsl2() {
+        let env = |name: &str| match name {
+            "WSL_DISTRO_NAME" => Some("Ubuntu-24.04".to_owned()),
+            "WSL_INTEROP" => Some("/run/WSL/1_interop".to_owned()),
+            _ => None,
+        };
+        let environment = WslDetector::new(fixture("wsl2"), env)
+            .with_wslinfo(|| Ok(parse_wslinfo_version("2.4.4\n")))
+            .detect()
+            .unwrap();
+        assert_eq!(
+            environment,
+            WslEnvironment {
+                generation: Some(WslGeneration::Wsl2),
+                kernel_release: Some("5.15.167.4-microsoft-standard-WSL2".into()),
+                kernel_version: Some(WslVersion::new(5, 15, 167)),
+                wsl_version: Some(WslVersion::new(2, 4, 4)),
+                distribution_name: Some("Ubuntu-24.04".into()),
+                interop: true,
+            }
+        );
+    }
+
+    #[test]
+    fn test_detect_wsl1() {
+        let environment = WslDetector::new(fixture("wsl1"), |_: &str| None)
+            .with_wslinfo(|| Err(io::ErrorKind::NotFound.into()))
+            .detect()
+            .unwrap();
+        assert_eq!(environment.generation, Some(WslGeneration::Wsl1));
+        assert_eq!(environment.wsl_version, None);
+        assert_eq!(environment.kernel_version, Some(WslVersion::new(4, 4, 0)));
+        assert!(!environment.interop);
+    }
+
+    #[test]
+    fn test_detect_native() {
+        assert_eq!(
+            WslDetector::new(fixture("native"), |_: &str| None).detect(),
+            None
+        );
+        // Variables inherited from a WSL shell are enough to report WSL.
+        let env = |name: &str| (name == "WSL_DISTRO_NAME").then(|| "Debian".to_owned());
+        let environment = WslDetector::new(fixture("native"), env)
+            .with_wslinfo(|| Ok(None))
+            .detect()
+            .unwrap();
+        assert_eq!(environment.generation, None);
+        assert_eq!(environment.distribution_name.as_deref(), Some("Debian"));
+    }
+}
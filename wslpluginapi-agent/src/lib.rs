@@ -0,0 +1,10 @@
+mod agent;
+mod environment;
+mod mountinfo;
+mod pid_namespace;
+mod rpc;
+pub use agent::*;
+pub use environment::*;
+pub use mountinfo::*;
+pub use pid_namespace::*;
+pub use rpc::*;
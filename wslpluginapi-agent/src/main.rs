@@ -0,0 +1,42 @@
+//! Companion agent started in a distribution by a WSL plugin.
+//!
+//! WSL connects the standard input and output of the agent to the socket returned by
+//! `ExecuteBinaryInDistribution`; the agent answers requests until the plugin closes it.
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+
+struct Stdio {
+    stdin: io::Stdin,
+    stdout: io::Stdout,
+}
+
+impl Read for Stdio {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.stdin.read(buf)
+    }
+}
+
+impl Write for Stdio {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.stdout.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+fn main() -> ExitCode {
+    let stdio = Stdio {
+        stdin: io::stdin(),
+        stdout: io::stdout(),
+    };
+    match wslpluginapi_agent::agent_server().serve(stdio) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            // Standard error ends up in the kernel log of the VM.
+            eprintln!("wslpluginapi-agent: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use std::fmt;
+use std::io;
+use std::str::FromStr;
+
+/// Default location of the mount table of the current process.
+pub const MOUNTINFO_PATH: &str = "/proc/self/mountinfo";
+
+/// One line of a `/proc/<pid>/mountinfo` file, with octal escapes decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountInfoEntry {
+    pub mount_id: u32,
+    pub parent_id: u32,
+    pub major: u32,
+    pub minor: u32,
+    /// Path of the directory of the file system mounted at `mount_point`.
+    pub root: String,
+    pub mount_point: String,
+    pub mount_options: Vec<String>,
+    /// Propagation fields such as `shared:1`.
+    pub optional_fields: Vec<String>,
+    pub fs_type: String,
+    pub source: String,
+    pub super_options: Vec<String>,
+}
+
+impl MountInfoEntry {
+    /// Returns whether the mount itself is read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.mount_options.iter().any(|option| option == "ro")
+    }
+
+    /// Returns the value of the file system option `name`.
+    pub fn super_option(&self, name: &str) -> Option<&str> {
+        self.super_options.iter().find_map(|option| {
+            option
+                .split_once('=')
+                .filter(|(key, _)| *key == name)
+                .map(|(_, value)| value)
+        })
+    }
+}
+
+/// Error returned when a mountinfo line is malformed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMountInfoError {
+    /// One-based line number, or 0 when a single entry was parsed.
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseMountInfoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "invalid mountinfo entry: {}", self.message)
+        } else {
+            write!(f, "invalid mountinfo line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for ParseMountInfoError {}
+
+/// Decodes the `\ooo` escapes the kernel uses for spaces, tabs, newlines and backslashes.
+fn unescape(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escape = bytes.get(i + 1..i + 4).filter(|digits| {
+            bytes[i] == b'\\' && digits.iter().all(|digit| (b'0'..=b'7').contains(digit))
+        });
+        match escape {
+            Some(digits) => {
+                let value = digits
+                    .iter()
+                    .fold(0u32, |value, digit| value * 8 + u32::from(digit - b'0'));
+                decoded.push(value as u8);
+                i += 4;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+impl FromStr for MountInfoEntry {
+    type Err = ParseMountInfoError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let error = |message: &str| ParseMountInfoError {
+            line: 0,
+            message: message.to_owned(),
+        };
+        let mut fields = line.split_ascii_whitespace();
+        let mut next = |name: &str| {
+            fields
+                .next()
+                .ok_or_else(|| error(&format!("missing {name}")))
+        };
+        let number = |value: &str, name: &str| {
+            value
+                .parse::<u32>()
+                .map_err(|_| error(&format!("invalid {name} {value:?}")))
+        };
+        let mount_id = number(next("mount ID")?, "mount ID")?;
+        let parent_id = number(next("parent ID")?, "parent ID")?;
+        let device = next("device")?;
+        let (major, minor) = device
+            .split_once(':')
+            .ok_or_else(|| error(&format!("invalid device {device:?}")))?;
+        let (major, minor) = (number(major, "major")?, number(minor, "minor")?);
+        let root = unescape(next("root")?);
+        let mount_point = unescape(next("mount point")?);
+        let mount_options = next("mount options")?.split(',').map(unescape).collect();
+        let mut optional_fields = Vec::new();
+        loop {
+            match next("separator")? {
+                "-" => break,
+                field => optional_fields.push(field.to_owned()),
+            }
+        }
+        let fs_type = unescape(next("file system type")?);
+        let source = unescape(next("source")?);
+        let super_options = next("super options")?.split(',').map(unescape).collect();
+        Ok(Self {
+            mount_id,
+            parent_id,
+            major,
+            minor,
+            root,
+            mount_point,
+            mount_options,
+            optional_fields,
+            fs_type,
+            source,
+            super_options,
+        })
+    }
+}
+
+/// Parses the content of a mountinfo file, skipping empty lines.
+pub fn parse_mountinfo(content: &str) -> Result<Vec<MountInfoEntry>, ParseMountInfoError> {
+    content
+        .lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(index, line)| {
+            line.parse()
+                .map_err(|error: ParseMountInfoError| ParseMountInfoError {
+                    line: index + 1,
+                    ..error
+                })
+        })
+        .collect()
+}
+
+/// Transport of a share exposed by the Windows host.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShareTransport {
+    Plan9,
+    VirtioFs,
+}
+
+/// A Windows folder shared by WSL, such as the ones mounted with `WSLPluginAPI_MountFolder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShareMount {
+    /// Name of the share, the `Name` passed to `MountFolder`.
+    pub name: String,
+    /// Windows folder behind the share, when WSL records it in the mount.
+    pub windows_path: Option<String>,
+    pub mount_point: String,
+    pub transport: ShareTransport,
+    pub read_only: bool,
+}
+
+fn is_windows_path(path: &str) -> bool {
+    let bytes = path.as_bytes();
+    path.starts_with(r"\\")
+        || (bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':')
+}
+
+impl ShareMount {
+    /// Recognizes plan9 and virtiofs mounts backed by the Windows host.
+    ///
+    /// For plan9 the share name comes from the `aname` option, whose `path=` parameter holds
+    /// the Windows folder. Virtiofs mounts are named by their tag, which is the mount source.
+    pub fn from_entry(entry: &MountInfoEntry) -> Option<Self> {
+        let (transport, name, windows_path) = match entry.fs_type.as_str() {
+            "9p" => {
+                let transport = entry.super_option("trans")?;
+                if transport != "virtio" && transport != "fd" {
+                    return None;
+                }
+                let mut aname = entry
+                    .super_option("aname")
+                    .unwrap_or(&entry.source)
+                    .split(';');
+                let name = aname.next().unwrap_or_default().to_owned();
+                let path = aname.find_map(|parameter| parameter.strip_prefix("path="));
+                (ShareTransport::Plan9, name, path.map(str::to_owned))
+            }
+            "virtiofs" => (ShareTransport::VirtioFs, entry.source.clone(), None),
+            _ => return None,
+        };
+        let windows_path =
+            windows_path.or_else(|| is_windows_path(&entry.source).then(|| entry.source.clone()));
+        Some(Self {
+            name,
+            windows_path,
+            mount_point: entry.mount_point.clone(),
+            transport,
+            read_only: entry.is_read_only(),
+        })
+    }
+}
+
+/// Returns the host shares among `entries`.
+pub fn share_mounts(entries: &[MountInfoEntry]) -> Vec<ShareMount> {
+    entries.iter().filter_map(ShareMount::from_entry).collect()
+}
+
+/// Reads [`MOUNTINFO_PATH`] and returns the host shares visible to the current process.
+pub fn current_share_mounts() -> io::Result<Vec<ShareMount>> {
+    let content = std::fs::read_to_string(MOUNTINFO_PATH)?;
+    let entries = parse_mountinfo(&content)
+        .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))?;
+    Ok(share_mounts(&entries))
+}
+
+/// Returns where the share called `name` is mounted for the current process.
+pub fn find_share_mount(name: &str) -> io::Result<Option<ShareMount>> {
+    Ok(current_share_mounts()?
+        .into_iter()
+        .find(|mount| mount.name == name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PLAN9: &str = include_str!("../fixtures/mountinfo-plan9.txt");
+    const VIRTIOFS: &str = include_str!("../fixtures/mountinfo-virtiofs.txt");
+
+    #[test]
+    fn test_parse_entry() {
+        let entries = parse_mountinfo(PLAN9).unwrap();
+        assert_eq!(entries.len(), 7);
+        let c = &entries[5];
+        assert_eq!(c.mount_id, 86);
+        assert_eq!((c.major, c.minor), (0, 54));
+        assert_eq!(c.mount_point, "/mnt/c");
+        assert_eq!(c.optional_fields, ["shared:30"]);
+        assert_eq!(c.fs_type, "9p");
+        assert_eq!(c.source, r"C:\");
+        assert_eq!(
+            c.super_option("aname"),
+            Some(r"drvfs;path=C:\;uid=1000;gid=1000;symlinkroot=/mnt/")
+        );
+        assert_eq!(entries[6].mount_point, "/mnt/shared data");
+    }
+
+    #[test]
+    fn test_plan9_shares() {
+        let shares = share_mounts(&parse_mountinfo(PLAN9).unwrap());
+        assert_eq!(
+            shares,
+            [
+                ShareMount {
+                    name: "drivers".into(),
+                    windows_path: None,
+                    mount_point: "/usr/lib/wsl/drivers".into(),
+                    transport: ShareTransport::Plan9,
+                    read_only: true,
+                },
+                ShareMount {
+                    name: "drvfs".into(),
+                    windows_path: Some(r"C:\".into()),
+                    mount_point: "/mnt/c".into(),
+                    transport: ShareTransport::Plan9,
+                    read_only: false,
+                },
+                ShareMount {
+                    name: "shared-data".into(),
+                    windows_path: Some(r"D:\Shared Data".into()),
+                    mount_point: "/mnt/shared data".into(),
+                    transport: ShareTransport::Plan9,
+                    read_only: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_virtiofs_shares() {
+        let shares = share_mounts(&parse_mountinfo(VIRTIOFS).unwrap());
+        let names: Vec<_> = shares.iter().map(|share| share.name.as_str()).collect();
+        assert_eq!(names, ["5e7c7d4a-0b4e-4a39-9a36-2f4b0c1c8a11", "tools"]);
+        assert!(shares
+            .iter()
+            .all(|share| share.transport == ShareTransport::VirtioFs));
+        assert!(shares[1].read_only);
+        assert_eq!(shares[1].windows_path, None);
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        let error = parse_mountinfo("61 66 8:48 / / rw - ext4 /dev/sdd rw\n\n62 x").unwrap_err();
+        assert_eq!(error.line, 3);
+        assert!("61 66 8:48 / / rw shared:1 ext4"
+            .parse::<MountInfoEntry>()
+            .is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Default mount point of procfs.
+pub const PROC_ROOT: &str = "/proc";
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Parses the target of a `/proc/<pid>/ns/pid` link, such as `pid:[4026531836]`.
+pub fn parse_namespace_link(link: &str) -> Option<u64> {
+    link.strip_prefix("pid:[")?.strip_suffix(']')?.parse().ok()
+}
+
+/// Access to the processes of a procfs mount, `/proc` by default.
+///
+/// Helpers started with `ExecuteBinary` run in the root PID namespace of the VM and see the
+/// processes of every distribution; the namespace of a process tells which one it belongs to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcFs {
+    root: PathBuf,
+}
+
+impl Default for ProcFs {
+    fn default() -> Self {
+        Self::new(PROC_ROOT)
+    }
+}
+
+impl ProcFs {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Returns the identifiers of the processes currently listed.
+    pub fn pids(&self) -> io::Result<Vec<u32>> {
+        let mut pids: Vec<u32> = fs::read_dir(&self.root)?
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+            .collect();
+        pids.sort_unstable();
+        Ok(pids)
+    }
+
+    /// Returns the inode number of the PID namespace of `pid`.
+    ///
+    /// This is the value WSL reports in `WSLDistributionInformation::PidNamespace`.
+    pub fn pid_namespace(&self, pid: u32) -> io::Result<u64> {
+        let link = fs::read_link(self.root.join(pid.to_string()).join("ns/pid"))?;
+        let link = link.to_string_lossy();
+        parse_namespace_link(&link)
+            .ok_or_else(|| invalid_data(format!("unexpected namespace link {link:?}")))
+    }
+
+    /// Returns the identifiers of `pid` in each nested PID namespace, outermost first.
+    ///
+    /// This is the `NSpid` line of `/proc/<pid>/status`.
+    pub fn namespace_pids(&self, pid: u32) -> io::Result<Vec<u32>> {
+        let status = fs::read_to_string(self.root.join(pid.to_string()).join("status"))?;
+        let line = status
+            .lines()
+            .find_map(|line| line.strip_prefix("NSpid:"))
+            .ok_or_else(|| invalid_data(format!("no NSpid line for process {pid}")))?;
+        line.split_ascii_whitespace()
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| invalid_data(format!("invalid NSpid value {value:?}")))
+            })
+            .collect()
+    }
+
+    /// Returns whether `pid` runs in the PID namespace `namespace`.
+    ///
+    /// A process that no longer exists does not belong to any namespace.
+    pub fn belongs_to_namespace(&self, pid: u32, namespace: u64) -> io::Result<bool> {
+        match self.pid_namespace(pid) {
+            Ok(actual) => Ok(actual == namespace),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Finds the init process of the PID namespace `namespace`, as seen from this procfs.
+    ///
+    /// The init is the process whose identifier is 1 inside the namespace. Processes that exit
+    /// during the scan, or whose namespace this process may not read, are skipped.
+    pub fn find_namespace_init(&self, namespace: u64) -> io::Result<Option<u32>> {
+        find_init(
+            self.pids()?,
+            |pid| self.belongs_to_namespace(pid, namespace),
+            |pid| self.namespace_pids(pid),
+        )
+    }
+}
+
+/// Returns the first of `pids` in the namespace tested by `in_namespace` whose innermost
+/// identifier, given by `namespace_pids`, is 1.
+fn find_init(
+    pids: Vec<u32>,
+    mut in_namespace: impl FnMut(u32) -> io::Result<bool>,
+    mut namespace_pids: impl FnMut(u32) -> io::Result<Vec<u32>>,
+) -> io::Result<Option<u32>> {
+    for pid in pids {
+        match in_namespace(pid) {
+            Ok(true) => {}
+            Ok(false) => continue,
+            // Without privileges, the namespaces of other users' processes cannot be read.
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => continue,
+            Err(e) => return Err(e),
+        }
+        match namespace_pids(pid) {
+            Ok(pids) if pids.last() == Some(&1) => return Ok(Some(pid)),
+            Ok(_) => {}
+            // The process exited while we were looking at it.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(None)
+}
+
+/// The PID namespace of a distribution as reported by the host.
+///
+/// Built from the `PidNamespace` and `InitPid` fields of `WSLDistributionInformation`, and
+/// usually sent to a helper running in the root namespace of the VM.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DistributionNamespace {
+    pub pid_namespace: u64,
+    /// Identifier of the distribution init in the root PID namespace.
+    pub init_pid: u32,
+}
+
+impl DistributionNamespace {
+    pub fn new(pid_namespace: u64, init_pid: u32) -> Self {
+        Self {
+            pid_namespace,
+            init_pid,
+        }
+    }
+
+    /// Checks that `init_pid` still designates the init of the distribution.
+    ///
+    /// This fails once the distribution stopped, even if its identifier has been reused.
+    pub fn verify(&self, proc: &ProcFs) -> io::Result<bool> {
+        if !proc.belongs_to_namespace(self.init_pid, self.pid_namespace)? {
+            return Ok(false);
+        }
+        Ok(proc.namespace_pids(self.init_pid)?.last() == Some(&1))
+    }
+
+    /// Returns whether `pid` belongs to the distribution.
+    pub fn contains(&self, proc: &ProcFs, pid: u32) -> io::Result<bool> {
+        proc.belongs_to_namespace(pid, self.pid_namespace)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    const ROOT_NAMESPACE: u64 = 4026531836;
+    const DISTRIBUTION_NAMESPACE: u64 = 4026532207;
+
+    /// Builds a fake procfs with the root init, a distribution init and one of its children.
+    fn fake_proc() -> ProcFs {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+        let root = std::env::temp_dir().join(format!(
+            "wslpluginapi-proc-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let processes: [(u32, u64, &str); 3] = [
+            (1, ROOT_NAMESPACE, "1"),
+            (312, DISTRIBUTION_NAMESPACE, "312\t1"),
+            (340, DISTRIBUTION_NAMESPACE, "340\t8"),
+        ];
+        for (pid, namespace, ns_pids) in processes {
+            let dir = root.join(pid.to_string());
+            fs::create_dir_all(dir.join("ns")).unwrap();
+            symlink(format!("pid:[{namespace}]"), dir.join("ns/pid")).unwrap();
+            fs::write(
+                dir.join("status"),
+                format!("Name:\tinit\nPid:\t{pid}\nNSpid:\t{ns_pids}\n"),
+            )
+            .unwrap();
+        }
+        fs::create_dir_all(root.join("sys")).unwrap();
+        ProcFs::new(root)
+    }
+
+    #[test]
+    fn test_parse_namespace_link() {
+        assert_eq!(parse_namespace_link("pid:[4026531836]"), Some(4026531836));
+        assert_eq!(parse_namespace_link("net:[4026531836]"), None);
+        assert_eq!(parse_namespace_link("pid:[]"), None);
+    }
+
+    #[test]
+    fn test_namespace_membership() {
+        let proc = fake_proc();
+        assert_eq!(proc.pids().unwrap(), [1, 312, 340]);
+        assert_eq!(proc.pid_namespace(340).unwrap(), DISTRIBUTION_NAMESPACE);
+        assert_eq!(proc.namespace_pids(340).unwrap(), [340, 8]);
+        assert!(proc
+            .belongs_to_namespace(340, DISTRIBUTION_NAMESPACE)
+            .unwrap());
+        assert!(!proc
+            .belongs_to_namespace(1, DISTRIBUTION_NAMESPACE)
+            .unwrap());
+        assert!(!proc
+            .belongs_to_namespace(999, DISTRIBUTION_NAMESPACE)
+            .unwrap());
+        assert_eq!(
+            proc.find_namespace_init(DISTRIBUTION_NAMESPACE).unwrap(),
+            Some(312)
+        );
+        assert_eq!(proc.find_namespace_init(42).unwrap(), None);
+        fs::remove_dir_all(proc.root()).unwrap();
+    }
+
+    #[test]
+    fn test_find_init_skips_unreadable_namespaces() {
+        let proc = fake_proc();
+        // An unprivileged helper cannot read the namespace of the root init.
+        let namespace_pids = |pid| proc.namespace_pids(pid);
+        let in_namespace = |pid| match pid {
+            1 => Err(io::Error::from(io::ErrorKind::PermissionDenied)),
+            pid => proc.belongs_to_namespace(pid, DISTRIBUTION_NAMESPACE),
+        };
+        let pids = proc.pids().unwrap();
+        assert_eq!(
+            find_init(pids.clone(), in_namespace, namespace_pids).unwrap(),
+            Some(312)
+        );
+        // Other errors still abort the scan.
+        let in_namespace = |_| Err(io::Error::from(io::ErrorKind::InvalidData));
+        assert!(find_init(pids, in_namespace, namespace_pids).is_err());
+        fs::remove_dir_all(proc.root()).unwrap();
+    }
+
+    #[test]
+    fn test_verify_distribution() {
+        let proc = fake_proc();
+        assert!(DistributionNamespace::new(DISTRIBUTION_NAMESPACE, 312)
+            .verify(&proc)
+            .unwrap());
+        // A child of the distribution is not its init.
+        assert!(!DistributionNamespace::new(DISTRIBUTION_NAMESPACE, 340)
+            .verify(&proc)
+            .unwrap());
+        // The distribution stopped and its init is gone.
+        assert!(!DistributionNamespace::new(DISTRIBUTION_NAMESPACE, 500)
+            .verify(&proc)
+            .unwrap());
+        assert!(DistributionNamespace::new(DISTRIBUTION_NAMESPACE, 312)
+            .contains(&proc, 340)
+            .unwrap());
+        fs::remove_dir_all(proc.root()).unwrap();
+    }
+}
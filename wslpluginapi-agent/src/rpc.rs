@@ -0,0 +1,541 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufReader, Read, Write};
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/// Version of the wire protocol, exchanged in the `Hello` frame of each side.
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// Largest payload accepted in a single frame.
+pub const MAX_FRAME_LEN: u32 = 16 * 1024 * 1024;
+
+const HELLO_MAGIC: [u8; 4] = *b"WSLA";
+
+const KIND_HELLO: u8 = 0;
+const KIND_REQUEST: u8 = 1;
+const KIND_RESPONSE: u8 = 2;
+const KIND_ERROR: u8 = 3;
+
+/// Error reported by the remote side for a single request.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteError {
+    pub code: i32,
+    pub message: String,
+}
+
+impl RemoteError {
+    /// No handler is registered for the requested method.
+    pub const METHOD_NOT_FOUND: i32 = -1;
+    /// The request payload could not be understood by the handler.
+    pub const INVALID_PARAMS: i32 = -2;
+    /// The handler failed.
+    pub const INTERNAL: i32 = -3;
+
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn invalid_params(message: impl Into<String>) -> Self {
+        Self::new(Self::INVALID_PARAMS, message)
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(Self::INTERNAL, message)
+    }
+}
+
+impl fmt::Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (code {})", self.message, self.code)
+    }
+}
+
+impl std::error::Error for RemoteError {}
+
+/// Error returned by the RPC client and server.
+#[derive(Debug)]
+pub enum RpcError {
+    Io(io::Error),
+    /// The peer closed the connection.
+    Closed,
+    /// The peer sent something that does not follow the protocol.
+    Protocol(String),
+    /// The peer speaks another version of the protocol.
+    UnsupportedVersion {
+        local: u16,
+        remote: u16,
+    },
+    /// The request failed on the remote side.
+    Remote(RemoteError),
+}
+
+impl fmt::Display for RpcError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(error) => write!(f, "RPC I/O error: {error}"),
+            Self::Closed => f.write_str("RPC connection closed by the peer"),
+            Self::Protocol(message) => write!(f, "RPC protocol error: {message}"),
+            Self::UnsupportedVersion { local, remote } => write!(
+                f,
+                "unsupported RPC protocol version {remote} (expected {local})"
+            ),
+            Self::Remote(error) => write!(f, "remote error: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for RpcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Remote(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RpcError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// A message of the protocol.
+///
+/// On the wire each frame is a little-endian `u32` length followed by that many bytes: a kind
+/// byte, then the fields of the frame. Integers are little-endian and strings are UTF-8.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Frame {
+    /// First frame sent by each side of a connection.
+    Hello {
+        version: u16,
+    },
+    Request {
+        id: u32,
+        method: String,
+        payload: Vec<u8>,
+    },
+    Response {
+        id: u32,
+        payload: Vec<u8>,
+    },
+    Error {
+        id: u32,
+        error: RemoteError,
+    },
+}
+
+impl Frame {
+    fn encode(&self) -> io::Result<Vec<u8>> {
+        let mut bytes = vec![0; 4];
+        match self {
+            Self::Hello { version } => {
+                bytes.push(KIND_HELLO);
+                bytes.extend_from_slice(&HELLO_MAGIC);
+                bytes.extend_from_slice(&version.to_le_bytes());
+            }
+            Self::Request {
+                id,
+                method,
+                payload,
+            } => {
+                let method_len = u16::try_from(method.len()).map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "method name is too long")
+                })?;
+                bytes.push(KIND_REQUEST);
+                bytes.extend_from_slice(&id.to_le_bytes());
+                bytes.extend_from_slice(&method_len.to_le_bytes());
+                bytes.extend_from_slice(method.as_bytes());
+                bytes.extend_from_slice(payload);
+            }
+            Self::Response { id, payload } => {
+                bytes.push(KIND_RESPONSE);
+                bytes.extend_from_slice(&id.to_le_bytes());
+                bytes.extend_from_slice(payload);
+            }
+            Self::Error { id, error } => {
+                bytes.push(KIND_ERROR);
+                bytes.extend_from_slice(&id.to_le_bytes());
+                bytes.extend_from_slice(&error.code.to_le_bytes());
+                bytes.extend_from_slice(error.message.as_bytes());
+            }
+        }
+        let len = u32::try_from(bytes.len() - 4)
+            .ok()
+            .filter(|len| *len <= MAX_FRAME_LEN)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "frame is too large"))?;
+        bytes[..4].copy_from_slice(&len.to_le_bytes());
+        Ok(bytes)
+    }
+
+    fn decode(bytes: &[u8]) -> Result<Self, RpcError> {
+        let mut decoder = Decoder(bytes);
+        let frame = match decoder.take(1)?[0] {
+            KIND_HELLO => {
+                if decoder.take(4)? != HELLO_MAGIC {
+                    return Err(RpcError::Protocol("bad hello magic".into()));
+                }
+                Self::Hello {
+                    version: decoder.u16()?,
+                }
+            }
+            KIND_REQUEST => {
+                let id = decoder.u32()?;
+                let method_len = decoder.u16()?;
+                let method = decoder.string(usize::from(method_len))?;
+                Self::Request {
+                    id,
+                    method,
+                    payload: decoder.rest().to_vec(),
+                }
+            }
+            KIND_RESPONSE => Self::Response {
+                id: decoder.u32()?,
+                payload: decoder.rest().to_vec(),
+            },
+            KIND_ERROR => {
+                let id = decoder.u32()?;
+                let code = decoder.u32()? as i32;
+                let message = decoder.string(decoder.0.len())?;
+                Self::Error {
+                    id,
+                    error: RemoteError { code, message },
+                }
+            }
+            kind => return Err(RpcError::Protocol(format!("unknown frame kind {kind}"))),
+        };
+        if !decoder.0.is_empty() {
+            return Err(RpcError::Protocol("trailing bytes in frame".into()));
+        }
+        Ok(frame)
+    }
+
+    /// Writes the frame and flushes `writer`.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.encode()?)?;
+        writer.flush()
+    }
+
+    /// Reads the next frame, or `None` if the stream ended cleanly between two frames.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Option<Self>, RpcError> {
+        let mut len = [0; 4];
+        let mut filled = 0;
+        while filled < len.len() {
+            match reader.read(&mut len[filled..]) {
+                Ok(0) if filled == 0 => return Ok(None),
+                Ok(0) => return Err(RpcError::Closed),
+                Ok(read) => filled += read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let len = u32::from_le_bytes(len);
+        if len > MAX_FRAME_LEN {
+            return Err(RpcError::Protocol(format!(
+                "frame of {len} bytes is too large"
+            )));
+        }
+        let mut bytes = vec![0; len as usize];
+        reader.read_exact(&mut bytes).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => RpcError::Closed,
+            _ => e.into(),
+        })?;
+        Self::decode(&bytes).map(Some)
+    }
+}
+
+struct Decoder<'a>(&'a [u8]);
+
+impl<'a> Decoder<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], RpcError> {
+        if self.0.len() < len {
+            return Err(RpcError::Protocol("truncated frame".into()));
+        }
+        let (taken, rest) = self.0.split_at(len);
+        self.0 = rest;
+        Ok(taken)
+    }
+
+    fn u16(&mut self) -> Result<u16, RpcError> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32, RpcError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self, len: usize) -> Result<String, RpcError> {
+        String::from_utf8(self.take(len)?.to_vec())
+            .map_err(|_| RpcError::Protocol("invalid UTF-8 string".into()))
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.0)
+    }
+}
+
+fn handshake<S: Read + Write>(stream: &mut S) -> Result<(), RpcError> {
+    Frame::Hello {
+        version: PROTOCOL_VERSION,
+    }
+    .write_to(stream)?;
+    match Frame::read_from(stream)? {
+        Some(Frame::Hello {
+            version: PROTOCOL_VERSION,
+        }) => Ok(()),
+        Some(Frame::Hello { version }) => Err(RpcError::UnsupportedVersion {
+            local: PROTOCOL_VERSION,
+            remote: version,
+        }),
+        Some(frame) => Err(RpcError::Protocol(format!(
+            "expected hello, received {frame:?}"
+        ))),
+        None => Err(RpcError::Closed),
+    }
+}
+
+/// Host side of a connection: sends requests and waits for their answer.
+#[derive(Debug)]
+pub struct Client<S: Read + Write> {
+    stream: S,
+    next_id: u32,
+}
+
+impl<S: Read + Write> Client<S> {
+    /// Exchanges `Hello` frames with the server on the other end of `stream`.
+    pub fn connect(mut stream: S) -> Result<Self, RpcError> {
+        handshake(&mut stream)?;
+        Ok(Self { stream, next_id: 1 })
+    }
+
+    /// Calls `method` and returns the payload of its response.
+    pub fn call(&mut self, method: &str, payload: &[u8]) -> Result<Vec<u8>, RpcError> {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        Frame::Request {
+            id,
+            method: method.to_owned(),
+            payload: payload.to_vec(),
+        }
+        .write_to(&mut self.stream)?;
+        match Frame::read_from(&mut self.stream)? {
+            Some(Frame::Response {
+                id: response_id,
+                payload,
+            }) if response_id == id => Ok(payload),
+            Some(Frame::Error {
+                id: response_id,
+                error,
+            }) if response_id == id => Err(RpcError::Remote(error)),
+            Some(frame) => Err(RpcError::Protocol(format!(
+                "expected the answer to request {id}, received {frame:?}"
+            ))),
+            None => Err(RpcError::Closed),
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+/// Returns the message of a panic payload caught with `catch_unwind`.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+type Handler = Box<dyn Fn(&[u8]) -> Result<Vec<u8>, RemoteError> + Send + Sync>;
+
+/// Agent side of a connection: dispatches requests to the registered methods.
+#[derive(Default)]
+pub struct Server {
+    methods: HashMap<String, Handler>,
+}
+
+impl fmt::Debug for Server {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Server")
+            .field("methods", &self.methods.keys().collect::<Vec<_>>())
+            .finish()
+    }
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the handler of `name`, replacing any previous one.
+    pub fn method<F>(mut self, name: impl Into<String>, handler: F) -> Self
+    where
+        F: Fn(&[u8]) -> Result<Vec<u8>, RemoteError> + Send + Sync + 'static,
+    {
+        self.methods.insert(name.into(), Box::new(handler));
+        self
+    }
+
+    /// Answers requests until the client closes the connection.
+    pub fn serve<S: Read + Write>(&self, mut stream: S) -> Result<(), RpcError> {
+        match handshake(&mut stream) {
+            // The client went away before sending anything.
+            Err(RpcError::Closed) => return Ok(()),
+            result => result?,
+        }
+        let mut reader = BufReader::new(stream);
+        while let Some(frame) = Frame::read_from(&mut reader)? {
+            let Frame::Request {
+                id,
+                method,
+                payload,
+            } = frame
+            else {
+                return Err(RpcError::Protocol(format!(
+                    "expected a request, received {frame:?}"
+                )));
+            };
+            let answer = match self.methods.get(&method) {
+                // A panicking handler fails its request, not the agent.
+                Some(handler) => match catch_unwind(AssertUnwindSafe(|| handler(&payload))) {
+                    Ok(Ok(payload)) => Frame::Response { id, payload },
+                    Ok(Err(error)) => Frame::Error { id, error },
+                    Err(panic) => Frame::Error {
+                        id,
+                        error: RemoteError::internal(format!(
+                            "method {method:?} panicked: {}",
+                            panic_message(panic.as_ref())
+                        )),
+                    },
+                },
+                None => Frame::Error {
+                    id,
+                    error: RemoteError::new(
+                        RemoteError::METHOD_NOT_FOUND,
+                        format!("unknown method {method:?}"),
+                    ),
+                },
+            };
+            answer.write_to(reader.get_mut())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_frame_round_trip() {
+        let frames = [
+            Frame::Hello { version: 7 },
+            Frame::Request {
+                id: 1,
+                method: "ping".into(),
+                payload: b"data".to_vec(),
+            },
+            Frame::Response {
+                id: 1,
+                payload: Vec::new(),
+            },
+            Frame::Error {
+                id: 2,
+                error: RemoteError::internal("failed"),
+            },
+        ];
+        let mut bytes = Vec::new();
+        for frame in &frames {
+            frame.write_to(&mut bytes).unwrap();
+        }
+        let mut reader = Cursor::new(bytes);
+        for frame in &frames {
+            assert_eq!(Frame::read_from(&mut reader).unwrap().as_ref(), Some(frame));
+        }
+        assert!(Frame::read_from(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_invalid_frames() {
+        let mut truncated = Cursor::new(vec![8, 0, 0, 0, KIND_RESPONSE, 1]);
+        assert!(matches!(
+            Frame::read_from(&mut truncated),
+            Err(RpcError::Closed)
+        ));
+        let mut oversized = Cursor::new((MAX_FRAME_LEN + 1).to_le_bytes().to_vec());
+        assert!(matches!(
+            Frame::read_from(&mut oversized),
+            Err(RpcError::Protocol(_))
+        ));
+        let mut unknown = Cursor::new(vec![1, 0, 0, 0, 9]);
+        assert!(matches!(
+            Frame::read_from(&mut unknown),
+            Err(RpcError::Protocol(_))
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_client_server() {
+        use std::os::unix::net::UnixStream;
+        let (host, guest) = UnixStream::pair().unwrap();
+        let server = std::thread::spawn(move || {
+            Server::new()
+                .method("echo", |payload| Ok(payload.to_vec()))
+                .method("fail", |_| Err(RemoteError::invalid_params("bad input")))
+                .method("panic", |_| panic!("handler bug"))
+                .serve(guest)
+        });
+        let mut client = Client::connect(host).unwrap();
+        assert_eq!(client.call("echo", b"hello").unwrap(), b"hello");
+        assert!(matches!(
+            client.call("fail", b""),
+            Err(RpcError::Remote(RemoteError {
+                code: RemoteError::INVALID_PARAMS,
+                ..
+            }))
+        ));
+        match client.call("panic", b"") {
+            Err(RpcError::Remote(error)) => {
+                assert_eq!(error.code, RemoteError::INTERNAL);
+                assert!(error.message.contains("handler bug"), "{error}");
+            }
+            result => panic!("unexpected result {result:?}"),
+        }
+        // The server is still answering after the panic.
+        assert_eq!(client.call("echo", b"again").unwrap(), b"again");
+        assert!(matches!(
+            client.call("missing", b""),
+            Err(RpcError::Remote(RemoteError {
+                code: RemoteError::METHOD_NOT_FOUND,
+                ..
+            }))
+        ));
+        drop(client);
+        server.join().unwrap().unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_version_mismatch() {
+        use std::os::unix::net::UnixStream;
+        let (mut host, guest) = UnixStream::pair().unwrap();
+        let server = std::thread::spawn(move || Server::new().serve(guest));
+        Frame::Hello { version: 99 }.write_to(&mut host).unwrap();
+        assert!(matches!(
+            server.join().unwrap(),
+            Err(RpcError::UnsupportedVersion { remote: 99, .. })
+        ));
+    }
+}
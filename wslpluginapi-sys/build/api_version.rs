@@ -0,0 +1,152 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Table of the package metadata listing the vendored `Microsoft.WSL.PluginApi` versions, which
+/// `cargo xtask nuget` also reads.
+const METADATA_KEY: &str = "wsl-plugin-api";
+
+/// Returns the versions listed in `[package.metadata.wsl-plugin-api] versions` of the manifest,
+/// oldest first.
+///
+/// Each one is selected by the `api-<major>-<minor>-<revision>` feature, which the manifest must
+/// declare, and gets a `wsl_plugin_api_ge_*` cfg so code can be gated on the release an item
+/// appeared in.
+pub(crate) fn vendored(manifest_dir: &Path) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+    let manifest_path = manifest_dir.join("Cargo.toml");
+    println!("cargo:rerun-if-changed={}", manifest_path.display());
+    let manifest: toml::Table = fs::read_to_string(&manifest_path)?.parse()?;
+    let mut versions = manifest
+        .get("package")
+        .and_then(|package| package.get("metadata"))
+        .and_then(|metadata| metadata.get(METADATA_KEY))
+        .and_then(|table| table.get("versions"))
+        .and_then(toml::Value::as_array)
+        .and_then(|versions| {
+            versions
+                .iter()
+                .map(|version| version.as_str().map(str::to_owned))
+                .collect::<Option<Vec<_>>>()
+        })
+        .ok_or_else(|| {
+            format!(
+                "package.metadata.{METADATA_KEY}.versions of {} must be an array of strings",
+                manifest_path.display()
+            )
+        })?;
+    let features = manifest.get("features").and_then(toml::Value::as_table);
+    let declared = |feature: &str| features.is_some_and(|features| features.contains_key(feature));
+    if let Some(version) = versions.iter().find(|version| !declared(&feature(version))) {
+        return Err(format!(
+            "Microsoft.WSL.PluginApi {version} is vendored but {} declares no `{}` feature",
+            manifest_path.display(),
+            feature(version)
+        )
+        .into());
+    }
+    versions.sort_by_key(|version| {
+        version
+            .split('.')
+            .map(|part| part.parse().unwrap_or(0))
+            .collect::<Vec<u32>>()
+    });
+    Ok(versions)
+}
+
+/// Returns the feature selecting `version`, such as `api-2-4-4`.
+fn feature(version: &str) -> String {
+    format!("api-{}", version.replace('.', "-"))
+}
+
+/// Returns the API version selected by the `api-*` features among the `vendored` ones.
+///
+/// Features are additive, so when several crates of a build ask for different versions the
+/// latest one wins; older plugins keep checking the runtime version with `require_version`.
+pub(crate) fn selected(vendored: &[String]) -> Result<&str, Box<dyn std::error::Error>> {
+    vendored
+        .iter()
+        .rev()
+        .find(|version| env::var_os(feature_env_var(&feature(version))).is_some())
+        .map(String::as_str)
+        .ok_or_else(|| {
+            let features: Vec<_> = vendored.iter().map(|version| feature(version)).collect();
+            format!(
+                "No WSL Plugin API version selected, enable one of the features: {}",
+                features.join(", ")
+            )
+            .into()
+        })
+}
+
+/// Name of the cfg set when `version` is the selected API version, such as
+/// `wsl_plugin_api_2_4_4`.
+fn exact_cfg(version: &str) -> String {
+    format!("wsl_plugin_api_{}", version.replace('.', "_"))
+}
+
+/// Name of the cfg set when the selected API version is `version` or later, such as
+/// `wsl_plugin_api_ge_2_1_2`.
+fn at_least_cfg(version: &str) -> String {
+    format!("wsl_plugin_api_ge_{}", version.replace('.', "_"))
+}
+
+/// Returns the cfgs describing `selected`: its exact cfg, then one `ge` cfg per vendored version
+/// up to it.
+fn cfgs(vendored: &[String], selected: &str) -> Vec<String> {
+    let reached = vendored
+        .iter()
+        .position(|version| version == selected)
+        .map_or(0, |index| index + 1);
+    std::iter::once(exact_cfg(selected))
+        .chain(
+            vendored[..reached]
+                .iter()
+                .map(|version| at_least_cfg(version)),
+        )
+        .collect()
+}
+
+/// Emits the cfgs of `selected` for this crate, and the `DEP_WSLPLUGINAPI_*` variables other
+/// build scripts read to derive them for dependent crates.
+pub(crate) fn emit(vendored: &[String], selected: &str) {
+    for version in vendored {
+        println!("cargo:rustc-check-cfg=cfg({})", exact_cfg(version));
+        println!("cargo:rustc-check-cfg=cfg({})", at_least_cfg(version));
+    }
+    let cfgs = cfgs(vendored, selected);
+    for cfg in &cfgs {
+        println!("cargo:rustc-cfg={cfg}");
+    }
+    let mut parts = selected.split('.');
+    println!("cargo::metadata=version={selected}");
+    for key in ["version_major", "version_minor", "version_revision"] {
+        println!("cargo::metadata={key}={}", parts.next().unwrap_or("0"));
+    }
+    println!("cargo::metadata=cfgs={}", cfgs.join(","));
+}
+
+fn feature_env_var(feature: &str) -> String {
+    format!(
+        "CARGO_FEATURE_{}",
+        feature.to_ascii_uppercase().replace('-', "_")
+    )
+}
+
+/// Returns the directory `cargo xtask nuget` vendored the package of `version` into.
+pub(crate) fn vendored_dir(
+    manifest_dir: &Path,
+    version: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let dir = manifest_dir
+        .join("third_party/Microsoft.WSL.PluginApi")
+        .join(version);
+    if !dir.is_dir() {
+        return Err(format!(
+            "Microsoft.WSL.PluginApi {version} is not vendored in {}, run `cargo xtask nuget` to \
+             vendor it",
+            dir.display()
+        )
+        .into());
+    }
+    Ok(dir)
+}
@@ -0,0 +1,133 @@
+//! Content-addressed cache of the bindgen output, so clean builds reuse the bindings of a
+//! previous build with the same inputs instead of running libclang again.
+//!
+//! Entries are named after a SHA-256 of the header, the clang target and arguments, the
+//! libclang version, the enabled features and the build script binary itself, which pins the
+//! bindgen version and its options. Each entry also records the SHA-256 of every file the
+//! header included, such as the stub or system `Windows.h` and the headers of the extra include
+//! directories, and is only reused while they are unchanged.
+
+use crate::header_source::HeaderSource;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Directory of the cache. The cache is disabled when it is unset or empty.
+pub(crate) const CACHE_DIR_ENV_VAR: &str = "WSLPLUGINAPI_SYS_BINDINGS_CACHE_DIR";
+
+/// Returns the cache directory, or `None` when caching is disabled.
+pub(crate) fn dir() -> Option<PathBuf> {
+    println!("cargo:rerun-if-env-changed={CACHE_DIR_ENV_VAR}");
+    env::var_os(CACHE_DIR_ENV_VAR)
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+}
+
+fn hex(digest: &[u8]) -> String {
+    digest.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn file_sha256(path: &Path) -> std::io::Result<String> {
+    Ok(hex(&Sha256::digest(fs::read(path)?)))
+}
+
+/// Computes the key of the bindings of `header` for `clang_target`.
+pub(crate) fn key(
+    header: &HeaderSource,
+    clang_target: &str,
+    stub_include_dir: Option<&Path>,
+) -> Result<String, Box<dyn Error>> {
+    let mut hasher = Sha256::new();
+    // Length-prefixed so that no two sets of inputs hash the same bytes.
+    let mut input = |name: &str, value: &[u8]| {
+        for part in [name.as_bytes(), value] {
+            hasher.update((part.len() as u64).to_le_bytes());
+            hasher.update(part);
+        }
+    };
+    input("header", header.sha256.as_bytes());
+    input("target", clang_target.as_bytes());
+    for arg in header.clang_args() {
+        input("clang arg", arg.as_bytes());
+    }
+    match stub_include_dir {
+        Some(dir) => input("stub", &fs::read(dir.join("Windows.h"))?),
+        None => input("system headers", &[]),
+    }
+    input("libclang", bindgen::clang_version().full.as_bytes());
+    let mut features: Vec<String> = env::vars_os()
+        .filter_map(|(name, _)| {
+            name.to_str()?
+                .strip_prefix("CARGO_FEATURE_")
+                .map(str::to_owned)
+        })
+        .collect();
+    features.sort();
+    for feature in &features {
+        input("feature", feature.as_bytes());
+    }
+    input("build script", &fs::read(env::current_exe()?)?);
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Returns `true` if every file listed in the `included` record of an entry still has the
+/// recorded hash.
+fn included_unchanged(included: &str) -> bool {
+    included.lines().all(|line| {
+        line.split_once(' ').is_some_and(|(sha256, path)| {
+            file_sha256(Path::new(path)).is_ok_and(|current| current == sha256)
+        })
+    })
+}
+
+/// Formats the `included` record of an entry, one `<sha256> <path>` line per file.
+fn included_record(included: &[PathBuf]) -> std::io::Result<String> {
+    let mut record = String::new();
+    for path in included {
+        record.push_str(&format!("{} {}\n", file_sha256(path)?, path.display()));
+    }
+    Ok(record)
+}
+
+/// Copies the cached bindings of `key` to `out_file`, or writes the bindings `generate` returns
+/// there and stores them in the cache with the files they were generated from.
+///
+/// Failing to store the bindings only warns, the cache is an optimisation.
+pub(crate) fn write_bindings(
+    cache_dir: Option<&Path>,
+    key: &str,
+    out_file: &Path,
+    generate: impl FnOnce() -> Result<(bindgen::Bindings, Vec<PathBuf>), Box<dyn Error>>,
+) -> Result<(), Box<dyn Error>> {
+    let Some(cache_dir) = cache_dir else {
+        return Ok(generate()?.0.write_to_file(out_file)?);
+    };
+    let entry = cache_dir.join(format!("{key}.rs"));
+    let included_entry = cache_dir.join(format!("{key}.included"));
+    if fs::read_to_string(&included_entry).is_ok_and(|included| included_unchanged(&included))
+        && fs::copy(&entry, out_file).is_ok()
+    {
+        return Ok(());
+    }
+    let (bindings, included) = generate()?;
+    bindings.write_to_file(out_file)?;
+    // Written aside then renamed, so concurrent builds never read a partial entry.
+    let partial = cache_dir.join(format!("{key}.rs.{}", std::process::id()));
+    let partial_included = cache_dir.join(format!("{key}.included.{}", std::process::id()));
+    let stored = fs::create_dir_all(cache_dir)
+        .and_then(|()| fs::write(&partial_included, included_record(&included)?))
+        .and_then(|()| fs::copy(out_file, &partial))
+        .and_then(|_| fs::rename(&partial, &entry))
+        .and_then(|()| fs::rename(&partial_included, &included_entry));
+    if let Err(error) = stored {
+        let _ = fs::remove_file(&partial);
+        let _ = fs::remove_file(&partial_included);
+        println!(
+            "cargo:warning=Cannot cache the bindings at {}: {error}",
+            entry.display()
+        );
+    }
+    Ok(())
+}
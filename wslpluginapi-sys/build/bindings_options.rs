@@ -0,0 +1,35 @@
+//! Customizations of the generated bindings, shared by bindgen and the header parser.
+
+/// Lines written before the generated items.
+pub(crate) const RAW_LINES: &[&str] = &[
+    "use crate::windows::core::*;",
+    "use crate::windows::Win32::Foundation::*;",
+    "use crate::windows::Win32::Security::*;",
+    "use crate::windows::Win32::Networking::WinSock::SOCKET;",
+    "#[allow(clippy::upper_case_acronyms)] type LPCWSTR = PCWSTR;",
+    "#[allow(clippy::upper_case_acronyms)] type LPCSTR = PCSTR;",
+    "#[allow(clippy::upper_case_acronyms)] type DWORD = u32;",
+    r#"#[cfg(feature = "hooks-field-names")]"#,
+    "use struct_field_names_as_array::FieldNamesAsSlice;",
+];
+
+/// Prefixes of the items to generate; the types they use come from the `windows` crate.
+pub(crate) const ALLOWLIST: &[&str] = &["WSL", "Wsl"];
+
+/// Derives added to the default `Debug, Copy, Clone` of a struct.
+pub(crate) fn extra_derives(struct_name: &str) -> &'static [&'static str] {
+    match struct_name {
+        "WSLVersion" => &["Eq", "PartialEq", "Ord", "PartialOrd", "Hash"],
+        _ => &[],
+    }
+}
+
+/// Attributes added to a struct.
+pub(crate) fn extra_attributes(struct_name: &str) -> &'static [&'static str] {
+    match struct_name {
+        "WSLPluginHooksV1" => {
+            &["#[cfg_attr(feature=\"hooks-field-names\", derive(FieldNamesAsSlice))]"]
+        }
+        _ => &[],
+    }
+}
@@ -0,0 +1,153 @@
+//! Translates the macros of `WslPluginApi.h` that bindgen drops into Rust items.
+//!
+//! Each macro the crate relies on must be present with the shape expected below, so a header
+//! update that changes one fails the build instead of silently diverging.
+
+use std::fmt::Write as _;
+use std::{fs, path::Path};
+
+type Error = Box<dyn std::error::Error>;
+
+const ENTRY_POINT_MACRO: &str = "WSLPLUGINAPI_ENTRYPOINTV1";
+const REQUIRES_UPDATE_MACRO: &str = "WSL_E_PLUGIN_REQUIRES_UPDATE";
+const REQUIRE_VERSION_MACRO: &str = "WSL_PLUGIN_REQUIRE_VERSION";
+
+#[derive(Debug)]
+struct Define {
+    name: String,
+    /// Parameters of a function-like macro.
+    params: Option<Vec<String>>,
+    body: String,
+}
+
+/// Returns the `#define` directives of `source`, with line continuations joined.
+fn defines(source: &str) -> Vec<Define> {
+    let mut defines = Vec::new();
+    let mut physical = source.lines();
+    while let Some(first) = physical.next() {
+        let mut line = first.to_owned();
+        while line.ends_with('\\') {
+            line.pop();
+            line.push_str(physical.next().unwrap_or_default());
+        }
+        let Some(rest) = line
+            .trim_start()
+            .strip_prefix('#')
+            .and_then(|directive| directive.trim_start().strip_prefix("define"))
+        else {
+            continue;
+        };
+        let rest = rest.trim_start();
+        let name_end = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        let (name, value) = rest.split_at(name_end);
+        // A parenthesis right after the name makes the macro function-like.
+        let (params, body) = match value
+            .strip_prefix('(')
+            .and_then(|value| value.split_once(')'))
+        {
+            Some((params, body)) => (
+                Some(
+                    params
+                        .split(',')
+                        .map(|param| param.trim().to_owned())
+                        .collect(),
+                ),
+                body,
+            ),
+            None => (None, value),
+        };
+        defines.push(Define {
+            name: name.to_owned(),
+            params,
+            body: body.split_whitespace().collect::<Vec<_>>().join(" "),
+        });
+    }
+    defines
+}
+
+fn find<'a>(defines: &'a [Define], name: &str) -> Result<&'a Define, Error> {
+    defines
+        .iter()
+        .find(|define| define.name == name)
+        .ok_or_else(|| format!("The header no longer defines {name}").into())
+}
+
+fn unexpected(define: &Define) -> Error {
+    format!(
+        "Unexpected definition of {} in the header: {:?}",
+        define.name, define.body
+    )
+    .into()
+}
+
+/// Rust items generated from the header macros.
+#[derive(Debug)]
+pub(crate) struct Macros(String);
+
+impl Macros {
+    pub(crate) fn write_to_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        fs::write(path, &self.0)
+    }
+}
+
+/// Translates the macros of `header_file_path`.
+///
+/// The output is meant to be included by `src/header_macros.rs`, which brings `make_hresult`
+/// and the `windows` items the values refer to into scope.
+pub(crate) fn generate<P: AsRef<Path>>(header_file_path: P) -> Result<Macros, Error> {
+    let defines = defines(&fs::read_to_string(header_file_path)?);
+    let mut out = String::from("/* generated from the header macros by the build script */\n");
+
+    let entry_point = find(&defines, ENTRY_POINT_MACRO)?;
+    let symbol = &entry_point.body;
+    if entry_point.params.is_some()
+        || symbol.is_empty()
+        || !symbol
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return Err(unexpected(entry_point));
+    }
+    writeln!(
+        out,
+        "/// Name of the function WSL calls to load the plugin (`{ENTRY_POINT_MACRO}`).\n\
+         pub const {ENTRY_POINT_MACRO}: &str = \"{symbol}\";\n\
+         /// [`{ENTRY_POINT_MACRO}`] as a C string, for symbol lookups.\n\
+         pub const {ENTRY_POINT_MACRO}_CSTR: &::core::ffi::CStr = c\"{symbol}\";"
+    )?;
+
+    let requires_update = find(&defines, REQUIRES_UPDATE_MACRO)?;
+    let arguments = requires_update
+        .body
+        .strip_prefix("MAKE_HRESULT(")
+        .and_then(|body| body.strip_suffix(')'))
+        .filter(|_| requires_update.params.is_none())
+        .ok_or_else(|| unexpected(requires_update))?;
+    writeln!(
+        out,
+        "/// Returned by plugins that need a more recent WSL.\n\
+         pub const {REQUIRES_UPDATE_MACRO}: HRESULT = make_hresult({arguments});"
+    )?;
+
+    // The body of the C macro is also valid Rust once `->` becomes `.`, which lets the tests
+    // run the header's own condition against `wsl_plugin_require_version!`.
+    let require_version = find(&defines, REQUIRE_VERSION_MACRO)?;
+    let Some([major, minor, revision, api]) = require_version.params.as_deref() else {
+        return Err(unexpected(require_version));
+    };
+    writeln!(
+        out,
+        "/// `{REQUIRE_VERSION_MACRO}` as written in the header.\n\
+         #[cfg(test)]\n\
+         #[allow(non_snake_case, unused_parens)]\n\
+         pub(crate) fn header_require_version({major}: u32, {minor}: u32, {revision}: u32, \
+         {api}: &crate::WSLPluginAPIV1) -> HRESULT {{\n\
+         {}\n\
+         crate::windows::Win32::Foundation::S_OK\n\
+         }}",
+        require_version.body.replace("->", ".")
+    )?;
+    Ok(Macros(out))
+}
@@ -0,0 +1,770 @@
+//! Generates the bindings of `WslPluginApi.h` without libclang.
+//!
+//! Only the subset of C used by the header is understood: structs, enums, typedefs of types
+//! and of function pointers, and object-like macros. The output mirrors what bindgen produces
+//! with the options of `header_processing`, layout tests included.
+
+use crate::bindings_options::{extra_attributes, extra_derives, ALLOWLIST, RAW_LINES};
+use crate::header_source::HeaderSource;
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::{fs, path::Path};
+
+type Error = Box<dyn std::error::Error>;
+/// Names and values of object-like macros.
+type Macros = Vec<(String, String)>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Ident(String),
+    Number(String),
+    Str(String),
+    Punct(char),
+}
+
+#[derive(Debug)]
+struct Lexed {
+    token: Token,
+    line: usize,
+}
+
+#[derive(Debug)]
+struct Comment {
+    line: usize,
+    text: String,
+    /// Whether the comment is the only thing on its line.
+    alone: bool,
+}
+
+#[derive(Debug, Clone)]
+enum CType {
+    Named(String),
+    Pointer { pointee: Box<CType>, is_const: bool },
+    Array(Box<CType>, usize),
+}
+
+#[derive(Debug)]
+struct Field {
+    name: String,
+    ty: CType,
+    doc: Option<String>,
+}
+
+#[derive(Debug)]
+enum Item {
+    Constant {
+        name: String,
+        value: String,
+    },
+    Struct {
+        name: String,
+        fields: Vec<Field>,
+        doc: Option<String>,
+    },
+    Enum {
+        name: String,
+        variants: Vec<(String, i64)>,
+        doc: Option<String>,
+    },
+    Alias {
+        name: String,
+        ty: CType,
+        doc: Option<String>,
+    },
+    FunctionPointer {
+        name: String,
+        parameters: Vec<Field>,
+        result: CType,
+        doc: Option<String>,
+    },
+}
+
+/// Bindings produced by [`generate`].
+pub(crate) struct Bindings(String);
+
+impl Bindings {
+    pub(crate) fn write_to_file<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        fs::write(path, &self.0)
+    }
+}
+
+/// Parses `header` and generates its bindings for a Windows target with pointers of
+/// `pointer_width` bits.
+pub(crate) fn generate(header: &HeaderSource, pointer_width: usize) -> Result<Bindings, Error> {
+    let source = fs::read_to_string(&header.path)?;
+    let (lines, macros) = preprocess(&source, header.defined_names())?;
+    let (tokens, comments) = lex(&lines)?;
+    let mut items: Vec<Item> = macros
+        .into_iter()
+        .filter_map(|(name, value)| {
+            macro_constant(&value).map(|value| Item::Constant { name, value })
+        })
+        .collect();
+    items.extend(Parser::new(tokens, comments).parse()?);
+    items.retain(|item| is_allowlisted(item.name()));
+    Ok(Bindings(
+        Generator::new(&items, pointer_width / 8).generate(&header.provenance())?,
+    ))
+}
+
+fn is_allowlisted(name: &str) -> bool {
+    ALLOWLIST.iter().any(|prefix| name.starts_with(prefix))
+}
+
+/// Resolves the preprocessor directives, leaving the skipped and directive lines empty so
+/// line numbers are kept. The `predefined` names count as defined from the start. Returns the
+/// object-like macros in definition order.
+fn preprocess<'a>(
+    source: &str,
+    predefined: impl IntoIterator<Item = &'a str>,
+) -> Result<(Vec<String>, Macros), Error> {
+    let mut lines: Vec<String> = Vec::new();
+    let mut macros: Macros = Vec::new();
+    let mut defined: HashSet<String> = predefined.into_iter().map(str::to_owned).collect();
+    // Whether each enclosing conditional block is active.
+    let mut conditions: Vec<bool> = Vec::new();
+    let mut physical = source.lines();
+    while let Some(first) = physical.next() {
+        let mut line = first.to_owned();
+        let mut continuations = 0;
+        while line.ends_with('\\') {
+            line.pop();
+            line.push_str(physical.next().unwrap_or_default());
+            continuations += 1;
+        }
+        let active = conditions.iter().all(|active| *active);
+        let trimmed = line.trim_start();
+        if let Some(directive) = trimmed.strip_prefix('#') {
+            let directive = directive.trim_start();
+            // The keyword may be directly followed by a comment, as in `#endif// NAME`.
+            let keyword_end = directive
+                .find(|c: char| !c.is_ascii_alphabetic())
+                .unwrap_or(directive.len());
+            let (keyword, rest) = directive.split_at(keyword_end);
+            let rest = rest.trim();
+            match keyword {
+                "ifdef" => conditions.push(defined.contains(rest)),
+                "ifndef" => conditions.push(!defined.contains(rest)),
+                "else" => {
+                    let last = conditions.last_mut().ok_or("#else without #if")?;
+                    *last = !*last;
+                }
+                "endif" => {
+                    conditions.pop().ok_or("#endif without #if")?;
+                }
+                "pragma" | "include" => {}
+                "define" if active => {
+                    let name_end = rest
+                        .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                        .unwrap_or(rest.len());
+                    let (name, value) = rest.split_at(name_end);
+                    defined.insert(name.to_owned());
+                    // Function-like macros cannot become constants.
+                    if !value.starts_with('(') {
+                        macros.push((name.to_owned(), value.trim().to_owned()));
+                    }
+                }
+                "define" => {}
+                _ => return Err(format!("Unsupported preprocessor directive: #{keyword}").into()),
+            }
+            line.clear();
+        } else if !active {
+            line.clear();
+        }
+        lines.push(line);
+        lines.extend(std::iter::repeat_n(String::new(), continuations));
+    }
+    if !conditions.is_empty() {
+        return Err("Unterminated #if block".into());
+    }
+    Ok((lines, macros))
+}
+
+/// Returns the Rust constant bindgen emits for a macro value, if it is a literal.
+fn macro_constant(value: &str) -> Option<String> {
+    if let Some(string) = value
+        .strip_prefix('"')
+        .and_then(|value| value.strip_suffix('"'))
+    {
+        if string.contains('\\') {
+            return None;
+        }
+        return Some(format!("&[u8; {}] = b\"{string}\\0\"", string.len() + 1));
+    }
+    let value = value.trim_end_matches(['u', 'U', 'l', 'L']);
+    let (negative, digits) = match value.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, value),
+    };
+    let parsed = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i128::from_str_radix(hex, 16).ok()?,
+        None => digits.parse::<i128>().ok()?,
+    };
+    let parsed = if negative { -parsed } else { parsed };
+    let ty = if parsed < 0 {
+        if i32::try_from(parsed).is_ok() {
+            "i32"
+        } else {
+            "i64"
+        }
+    } else if u32::try_from(parsed).is_ok() {
+        "u32"
+    } else {
+        "u64"
+    };
+    Some(format!("{ty} = {parsed}"))
+}
+
+fn lex(lines: &[String]) -> Result<(Vec<Lexed>, Vec<Comment>), Error> {
+    let mut tokens = Vec::new();
+    let mut comments = Vec::new();
+    for (index, line) in lines.iter().enumerate() {
+        let line_number = index + 1;
+        let mut rest = line.as_str();
+        let mut alone = true;
+        loop {
+            rest = rest.trim_start();
+            let Some(c) = rest.chars().next() else {
+                break;
+            };
+            if let Some(text) = rest.strip_prefix("//") {
+                comments.push(Comment {
+                    line: line_number,
+                    text: text.trim_end().to_owned(),
+                    alone,
+                });
+                break;
+            }
+            alone = false;
+            let length = if c.is_ascii_alphabetic() || c == '_' {
+                let length = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                    .unwrap_or(rest.len());
+                tokens.push(Lexed {
+                    token: Token::Ident(rest[..length].to_owned()),
+                    line: line_number,
+                });
+                length
+            } else if c.is_ascii_digit() {
+                let length = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric())
+                    .unwrap_or(rest.len());
+                tokens.push(Lexed {
+                    token: Token::Number(rest[..length].to_owned()),
+                    line: line_number,
+                });
+                length
+            } else if c == '"' {
+                let end = rest[1..]
+                    .find('"')
+                    .ok_or_else(|| format!("Unterminated string on line {line_number}"))?;
+                tokens.push(Lexed {
+                    token: Token::Str(rest[1..=end].to_owned()),
+                    line: line_number,
+                });
+                end + 2
+            } else if "{}()[];,*=-".contains(c) {
+                tokens.push(Lexed {
+                    token: Token::Punct(c),
+                    line: line_number,
+                });
+                1
+            } else {
+                return Err(format!("Unexpected character {c:?} on line {line_number}").into());
+            };
+            rest = &rest[length..];
+        }
+    }
+    Ok((tokens, comments))
+}
+
+struct Parser {
+    tokens: Vec<Lexed>,
+    position: usize,
+    comments: Vec<Comment>,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Lexed>, comments: Vec<Comment>) -> Self {
+        Self {
+            tokens,
+            position: 0,
+            comments,
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|lexed| &lexed.token)
+    }
+
+    fn line(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .or(self.tokens.last())
+            .map_or(0, |lexed| lexed.line)
+    }
+
+    fn next(&mut self) -> Result<Token, Error> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .ok_or("Unexpected end of header")?
+            .token
+            .clone();
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn error<T>(&self, message: &str) -> Result<T, Error> {
+        Err(format!("{message} on line {}", self.line()).into())
+    }
+
+    fn expect_punct(&mut self, expected: char) -> Result<(), Error> {
+        match self.next()? {
+            Token::Punct(c) if c == expected => Ok(()),
+            token => {
+                self.position -= 1;
+                self.error(&format!("Expected {expected:?}, found {token:?}"))
+            }
+        }
+    }
+
+    fn eat_punct(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(&Token::Punct(expected));
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Ident(ident)) if ident == keyword);
+        if found {
+            self.position += 1;
+        }
+        found
+    }
+
+    fn ident(&mut self) -> Result<String, Error> {
+        match self.next()? {
+            Token::Ident(ident) => Ok(ident),
+            token => {
+                self.position -= 1;
+                self.error(&format!("Expected an identifier, found {token:?}"))
+            }
+        }
+    }
+
+    /// Joins the comments alone on the lines right above `line`, like clang does.
+    fn leading_doc(&self, line: usize) -> Option<String> {
+        let mut lines = Vec::new();
+        let mut expected = line.checked_sub(1)?;
+        for comment in self.comments.iter().rev().skip_while(|c| c.line >= line) {
+            if comment.line != expected || !comment.alone {
+                break;
+            }
+            lines.push(comment.text.as_str());
+            expected -= 1;
+        }
+        lines.reverse();
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    /// Returns the comment following a declaration on its last line.
+    fn trailing_doc(&self, line: usize) -> Option<String> {
+        self.comments
+            .iter()
+            .find(|comment| comment.line == line && !comment.alone)
+            .map(|comment| comment.text.clone())
+    }
+
+    fn parse(mut self) -> Result<Vec<Item>, Error> {
+        let mut items = Vec::new();
+        while self.peek().is_some() {
+            let doc = self.leading_doc(self.line());
+            if self.eat_keyword("typedef") {
+                items.push(self.parse_typedef(doc)?);
+            } else if self.eat_keyword("struct") {
+                items.push(self.parse_struct(doc)?);
+            } else if self.eat_keyword("enum") {
+                items.push(self.parse_enum(doc)?);
+            } else {
+                return self.error("Expected a declaration");
+            }
+        }
+        Ok(items)
+    }
+
+    fn parse_struct(&mut self, doc: Option<String>) -> Result<Item, Error> {
+        let name = self.ident()?;
+        self.expect_punct('{')?;
+        let mut fields = Vec::new();
+        while !self.eat_punct('}') {
+            let line = self.line();
+            let leading = self.leading_doc(line);
+            let field = self.parse_declaration()?;
+            let end_line = self.line();
+            self.expect_punct(';')?;
+            let doc = leading.or_else(|| self.trailing_doc(end_line));
+            fields.push(Field { doc, ..field });
+        }
+        self.expect_punct(';')?;
+        Ok(Item::Struct { name, fields, doc })
+    }
+
+    fn parse_enum(&mut self, doc: Option<String>) -> Result<Item, Error> {
+        let name = self.ident()?;
+        self.expect_punct('{')?;
+        let mut variants = Vec::new();
+        let mut next_value = 0;
+        while !self.eat_punct('}') {
+            let variant = self.ident()?;
+            if self.eat_punct('=') {
+                let negative = self.eat_punct('-');
+                let Token::Number(number) = self.next()? else {
+                    return self.error("Expected an enumerator value");
+                };
+                let value: i64 = match number.strip_prefix("0x") {
+                    Some(hex) => i64::from_str_radix(hex, 16)?,
+                    None => number.parse()?,
+                };
+                next_value = if negative { -value } else { value };
+            }
+            variants.push((variant, next_value));
+            next_value += 1;
+            if !self.eat_punct(',') {
+                self.expect_punct('}')?;
+                break;
+            }
+        }
+        self.expect_punct(';')?;
+        Ok(Item::Enum {
+            name,
+            variants,
+            doc,
+        })
+    }
+
+    fn parse_typedef(&mut self, doc: Option<String>) -> Result<Item, Error> {
+        let result = self.parse_type()?;
+        if self.eat_punct('(') {
+            self.expect_punct('*')?;
+            let name = self.ident()?;
+            self.expect_punct(')')?;
+            self.expect_punct('(')?;
+            let mut parameters = Vec::new();
+            if !self.eat_punct(')') {
+                loop {
+                    if self.eat_keyword("void") && self.eat_punct(')') {
+                        break;
+                    }
+                    let parameter = self.parse_declaration()?;
+                    parameters.push(parameter);
+                    if !self.eat_punct(',') {
+                        self.expect_punct(')')?;
+                        break;
+                    }
+                }
+            }
+            self.expect_punct(';')?;
+            return Ok(Item::FunctionPointer {
+                name,
+                parameters,
+                result,
+                doc,
+            });
+        }
+        let Field { name, ty, .. } = self.parse_declarator(result)?;
+        self.expect_punct(';')?;
+        Ok(Item::Alias { name, ty, doc })
+    }
+
+    /// Parses the type specifiers and qualifiers in front of a declarator.
+    fn parse_type(&mut self) -> Result<CType, Error> {
+        const BUILTIN_WORDS: [&str; 6] = ["unsigned", "signed", "int", "long", "short", "char"];
+        let mut is_const = false;
+        let mut words: Vec<String> = Vec::new();
+        loop {
+            if self.eat_keyword("const") {
+                is_const = true;
+            } else if self.eat_keyword("struct") || self.eat_keyword("enum") {
+                words.push(self.ident()?);
+                break;
+            } else {
+                match self.peek() {
+                    Some(Token::Ident(ident)) if BUILTIN_WORDS.contains(&ident.as_str()) => {
+                        words.push(ident.clone());
+                        self.position += 1;
+                    }
+                    Some(Token::Ident(ident)) if words.is_empty() => {
+                        words.push(ident.clone());
+                        self.position += 1;
+                        break;
+                    }
+                    _ => break,
+                }
+            }
+        }
+        while self.eat_keyword("const") {
+            is_const = true;
+        }
+        if words.is_empty() {
+            return self.error("Expected a type");
+        }
+        let mut ty = CType::Named(words.join(" "));
+        while self.eat_punct('*') {
+            ty = CType::Pointer {
+                pointee: Box::new(ty),
+                is_const,
+            };
+            // Qualifiers of the pointer itself make no difference in Rust.
+            is_const = false;
+            while self.eat_keyword("const") {}
+        }
+        Ok(ty)
+    }
+
+    fn parse_declaration(&mut self) -> Result<Field, Error> {
+        let ty = self.parse_type()?;
+        self.parse_declarator(ty)
+    }
+
+    fn parse_declarator(&mut self, ty: CType) -> Result<Field, Error> {
+        let name = self.ident()?;
+        let mut ty = ty;
+        if self.eat_punct('[') {
+            let Token::Number(length) = self.next()? else {
+                return self.error("Expected an array length");
+            };
+            self.expect_punct(']')?;
+            ty = CType::Array(Box::new(ty), length.parse()?);
+        }
+        Ok(Field {
+            name,
+            ty,
+            doc: None,
+        })
+    }
+}
+
+impl Item {
+    fn name(&self) -> &str {
+        match self {
+            Self::Constant { name, .. }
+            | Self::Struct { name, .. }
+            | Self::Enum { name, .. }
+            | Self::Alias { name, .. }
+            | Self::FunctionPointer { name, .. } => name,
+        }
+    }
+}
+
+/// Size and alignment of a type.
+type Layout = (usize, usize);
+
+struct Generator<'a> {
+    items: &'a [Item],
+    pointer_size: usize,
+    output: String,
+}
+
+impl<'a> Generator<'a> {
+    fn new(items: &'a [Item], pointer_size: usize) -> Self {
+        Self {
+            items,
+            pointer_size,
+            output: String::new(),
+        }
+    }
+
+    fn find(&self, name: &str) -> Option<&'a Item> {
+        self.items.iter().find(|item| item.name() == name)
+    }
+
+    fn rust_type(&self, ty: &CType) -> String {
+        match ty {
+            CType::Named(name) => match name.as_str() {
+                "uint8_t" => "u8".into(),
+                "uint16_t" => "u16".into(),
+                "uint32_t" => "u32".into(),
+                "uint64_t" => "u64".into(),
+                "int8_t" => "i8".into(),
+                "int16_t" => "i16".into(),
+                "int32_t" => "i32".into(),
+                "int64_t" => "i64".into(),
+                "char" => "::std::os::raw::c_char".into(),
+                "int" | "signed int" | "signed" => "::std::os::raw::c_int".into(),
+                "unsigned int" | "unsigned" => "::std::os::raw::c_uint".into(),
+                "void" => "::std::os::raw::c_void".into(),
+                name => name.into(),
+            },
+            CType::Pointer { pointee, is_const } => {
+                let mutability = if *is_const { "const" } else { "mut" };
+                format!("*{mutability} {}", self.rust_type(pointee))
+            }
+            CType::Array(element, length) => format!("[{}; {length}]", self.rust_type(element)),
+        }
+    }
+
+    /// Layout of a type following the Windows ABI, where `long` is 32 bits and 64-bit integers
+    /// are 8-byte aligned on every architecture.
+    fn layout(&self, ty: &CType) -> Result<Layout, Error> {
+        let pointer = (self.pointer_size, self.pointer_size);
+        Ok(match ty {
+            CType::Pointer { .. } => pointer,
+            CType::Array(element, length) => {
+                let (size, align) = self.layout(element)?;
+                (size * length, align)
+            }
+            CType::Named(name) => match name.as_str() {
+                "uint8_t" | "int8_t" | "char" => (1, 1),
+                "uint16_t" | "int16_t" | "short" | "unsigned short" => (2, 2),
+                "uint32_t" | "int32_t" | "int" | "unsigned int" | "long" | "unsigned long"
+                | "DWORD" | "BOOL" | "HRESULT" => (4, 4),
+                "uint64_t" | "int64_t" | "long long" | "unsigned long long" => (8, 8),
+                "HANDLE" | "PSID" | "LPCWSTR" | "LPCSTR" | "SOCKET" => pointer,
+                "GUID" => (16, 4),
+                name => match self.find(name) {
+                    Some(Item::Struct { fields, .. }) => self.struct_layout(fields)?.0,
+                    Some(Item::Enum { .. }) => (4, 4),
+                    Some(Item::Alias { ty, .. }) => self.layout(ty)?,
+                    Some(Item::FunctionPointer { .. }) => pointer,
+                    _ => return Err(format!("Unknown type {name}").into()),
+                },
+            },
+        })
+    }
+
+    /// Returns the layout of a struct and the offsets of its fields.
+    fn struct_layout(&self, fields: &[Field]) -> Result<(Layout, Vec<usize>), Error> {
+        let mut offset: usize = 0;
+        let mut struct_align = 1;
+        let mut offsets = Vec::new();
+        for field in fields {
+            let (size, align) = self.layout(&field.ty)?;
+            offset = offset.next_multiple_of(align);
+            offsets.push(offset);
+            offset += size;
+            struct_align = struct_align.max(align);
+        }
+        Ok((
+            (offset.next_multiple_of(struct_align), struct_align),
+            offsets,
+        ))
+    }
+
+    fn doc(&mut self, doc: &Option<String>, indent: &str) {
+        if let Some(doc) = doc {
+            let escaped = doc.escape_default().to_string().replace("\\'", "'");
+            let _ = writeln!(self.output, "{indent}#[doc = \"{escaped}\"]");
+        }
+    }
+
+    fn generate(mut self, provenance: &str) -> Result<String, Error> {
+        self.output
+            .push_str("/* generated from the header by the build script's header parser */\n\n");
+        let _ = writeln!(self.output, "{provenance}");
+        for line in RAW_LINES {
+            let _ = writeln!(self.output, "{line}");
+        }
+        self.output.push('\n');
+        for item in self.items {
+            match item {
+                Item::Constant { name, value } => {
+                    let _ = writeln!(self.output, "pub const {name}: {value};");
+                }
+                Item::Struct { name, fields, doc } => self.generate_struct(name, fields, doc)?,
+                Item::Enum {
+                    name,
+                    variants,
+                    doc,
+                } => {
+                    for (variant, value) in variants {
+                        let _ =
+                            writeln!(self.output, "pub const {name}_{variant}: {name} = {value};");
+                    }
+                    self.doc(doc, "");
+                    let _ = writeln!(self.output, "pub type {name} = ::std::os::raw::c_int;");
+                }
+                Item::Alias { name, ty, doc } => {
+                    self.doc(doc, "");
+                    let ty = self.rust_type(ty);
+                    let _ = writeln!(self.output, "pub type {name} = {ty};");
+                }
+                Item::FunctionPointer {
+                    name,
+                    parameters,
+                    result,
+                    doc,
+                } => {
+                    self.doc(doc, "");
+                    let parameters: Vec<String> = parameters
+                        .iter()
+                        .map(|parameter| {
+                            format!("{}: {}", parameter.name, self.rust_type(&parameter.ty))
+                        })
+                        .collect();
+                    let result = match result {
+                        CType::Named(name) if name == "void" => String::new(),
+                        result => format!(" -> {}", self.rust_type(result)),
+                    };
+                    let _ = writeln!(
+                        self.output,
+                        "pub type {name} = ::std::option::Option<unsafe extern \"C\" fn({}){result}>;",
+                        parameters.join(", ")
+                    );
+                }
+            }
+        }
+        Ok(self.output)
+    }
+
+    fn generate_struct(
+        &mut self,
+        name: &str,
+        fields: &[Field],
+        doc: &Option<String>,
+    ) -> Result<(), Error> {
+        self.doc(doc, "");
+        self.output.push_str("#[repr(C)]\n");
+        let derives: Vec<&str> = ["Debug", "Copy", "Clone"]
+            .into_iter()
+            .chain(extra_derives(name).iter().copied())
+            .collect();
+        let _ = writeln!(self.output, "#[derive({})]", derives.join(", "));
+        for attribute in extra_attributes(name) {
+            let _ = writeln!(self.output, "{attribute}");
+        }
+        let _ = writeln!(self.output, "pub struct {name} {{");
+        for field in fields {
+            self.doc(&field.doc, "    ");
+            let ty = self.rust_type(&field.ty);
+            let _ = writeln!(self.output, "    pub {}: {ty},", field.name);
+        }
+        self.output.push_str("}\n");
+        let ((size, align), offsets) = self.struct_layout(fields)?;
+        self.output
+            .push_str("#[allow(clippy::unnecessary_operation, clippy::identity_op)]\n");
+        self.output.push_str("const _: () = {\n");
+        let _ = writeln!(
+            self.output,
+            "    [\"Size of {name}\"][::std::mem::size_of::<{name}>() - {size}usize];"
+        );
+        let _ = writeln!(
+            self.output,
+            "    [\"Alignment of {name}\"][::std::mem::align_of::<{name}>() - {align}usize];"
+        );
+        for (field, offset) in fields.iter().zip(offsets) {
+            let _ = writeln!(
+                self.output,
+                "    [\"Offset of field: {name}::{0}\"][::std::mem::offset_of!({name}, {0}) - {offset}usize];",
+                field.name
+            );
+        }
+        self.output.push_str("};\n");
+        Ok(())
+    }
+}
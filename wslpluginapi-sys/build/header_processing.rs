@@ -1,59 +1,59 @@
+use crate::bindings_options::{extra_attributes, extra_derives, ALLOWLIST, RAW_LINES};
+use crate::header_source::HeaderSource;
 #[cfg(unix)]
 use crate::WSL_PLUGIN_API_HEADER_FILE_NAME;
 use bindgen::callbacks::{ParseCallbacks, TypeKind};
 use cfg_if::cfg_if;
 #[cfg(unix)]
 use cow_utils::CowUtils;
-use std::{borrow::Cow, collections::HashMap, path::Path, vec};
+use std::sync::{Arc, Mutex};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+};
 #[cfg(unix)]
-use std::{env, fs, io::Write, path::PathBuf};
+use std::{env, fs, io::Write};
 
-#[derive(Debug)]
-struct BindgenCallback;
-
-impl BindgenCallback {}
+#[derive(Debug, Default)]
+struct BindgenCallback {
+    /// Every file the header includes, directly or not, in the order clang opened them.
+    included: Arc<Mutex<Vec<PathBuf>>>,
+}
 
 impl ParseCallbacks for BindgenCallback {
+    fn include_file(&self, filename: &str) {
+        self.included.lock().unwrap().push(filename.into());
+    }
+
     fn add_derives(&self, info: &bindgen::callbacks::DeriveInfo<'_>) -> Vec<String> {
-        if info.kind == TypeKind::Struct && info.name == "WSLVersion" {
-            ["Eq", "PartialEq", "Ord", "PartialOrd", "Hash"]
-                .into_iter()
-                .map(|s| s.into())
+        if info.kind == TypeKind::Struct {
+            extra_derives(info.name)
+                .iter()
+                .map(|s| (*s).into())
                 .collect()
         } else {
             Vec::default()
         }
     }
 
-    fn add_attributes(&self, _info: &bindgen::callbacks::AttributeInfo<'_>) -> Vec<String> {
-        if _info.kind == TypeKind::Struct && _info.name == "WSLPluginHooksV1" {
-            vec!["#[cfg_attr(feature=\"hooks-field-names\", derive(FieldNamesAsSlice))]".into()]
+    fn add_attributes(&self, info: &bindgen::callbacks::AttributeInfo<'_>) -> Vec<String> {
+        if info.kind == TypeKind::Struct {
+            extra_attributes(info.name)
+                .iter()
+                .map(|s| (*s).into())
+                .collect()
         } else {
             Vec::default()
         }
     }
 }
 
-fn rust_to_llvm_target() -> HashMap<&'static str, &'static str> {
-    HashMap::from([
-        ("x86_64-pc-windows-gnu", "x86_64-w64-mingw32"),
-        ("i686-pc-windows-gnu", "i686-w64-mingw32"),
-        ("aarch64-pc-windows-gnu", "aarch64-w64-mingw32"),
-        ("x86_64-pc-windows-gnullvm", "x86_64-w64-mingw32"),
-        ("i686-pc-windows-gnullvm", "i686-w64-mingw32"),
-        ("aarch64-pc-windows-gnullvm ", "aarch64-w64-mingw32"),
-        ("x86_64-pc-windows-msvc", "x86_64-windows-msvc"),
-        ("i686-pc-windows-msvc", "i686-windows-msvc"),
-        ("aarch64-pc-windows-msvc", "aarch64-windows-msvc"),
-    ])
-}
-
 /// If the host is not Windows, replace `Windows.h` with `windows.h` in a temporary file.
 #[cfg(unix)]
 fn preprocess_header<'a, P: 'a + AsRef<Path>>(
     header_path: &'a P,
 ) -> Result<Cow<'a, Path>, Box<dyn std::error::Error>> {
-    let content = fs::read_to_string(&header_path)?;
+    let content = fs::read_to_string(header_path)?;
     let target_env = std::env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default();
     let new_content = if target_env == "msvc" {
         content.cow_replace("windows.h", "Windows.h")
@@ -75,44 +75,58 @@ fn preprocess_header<'a, P: 'a + AsRef<Path>>(
     Ok(result)
 }
 
-pub(crate) fn process<P: AsRef<Path>, S: AsRef<str>>(
-    header_file_path: P,
-    host: S,
-    target: S,
-) -> Result<bindgen::Bindings, Box<dyn std::error::Error>> {
-    let host = host.as_ref();
-    let target = target.as_ref();
+/// Generates the bindings of `header` for `clang_target`, returning them with the files the
+/// header includes.
+///
+/// When `stub_include_dir` is set, `<Windows.h>` is resolved to the stub header it contains
+/// and the system headers are not searched at all.
+pub(crate) fn process(
+    header: &HeaderSource,
+    clang_target: &str,
+    stub_include_dir: Option<&Path>,
+) -> Result<(bindgen::Bindings, Vec<PathBuf>), Box<dyn std::error::Error>> {
+    let callback = BindgenCallback::default();
+    let included = Arc::clone(&callback.included);
     // Here we use cow to have the same type and avoiding clowning the PathBuff
-    let header_file_path: Cow<'_, Path> = {
+    let header_file_path: Cow<'_, Path> = if stub_include_dir.is_some() {
+        // The stub is named like the include, whatever the case sensitivity of the file system.
+        Cow::Borrowed(header.path.as_ref())
+    } else {
         cfg_if! {
             if #[cfg(unix)] {
-                preprocess_header(&header_file_path)?
+                preprocess_header(&header.path)?
             } else {
-                Cow::Borrowed(header_file_path.as_ref())
+                Cow::Borrowed(header.path.as_ref())
             }
         }
     };
     let mut builder = bindgen::Builder::default()
         .header(header_file_path.to_str().unwrap())
-        .raw_line("use windows::core::*;")
-        .raw_line("use windows::Win32::Foundation::*;")
-        .raw_line("use windows::Win32::Security::*;")
-        .raw_line("use windows::Win32::Networking::WinSock::SOCKET;")
-        .raw_line("#[allow(clippy::upper_case_acronyms)] type LPCWSTR = PCWSTR;")
-        .raw_line("#[allow(clippy::upper_case_acronyms)] type LPCSTR = PCSTR;")
-        .raw_line("#[allow(clippy::upper_case_acronyms)] type DWORD = u32;")
-        .raw_line(r#"#[cfg(feature = "hooks-field-names")]"#)
-        .raw_line("use struct_field_names_as_array::FieldNamesAsSlice;")
-        .allowlist_item("WSL.*")
-        .allowlist_item("Wsl.*")
         .clang_arg("-fparse-all-comments")
+        .clang_arg(format!("--target={clang_target}"))
+        // Before the stub, so the extra directories can provide their own headers.
+        .clang_args(header.clang_args())
         .allowlist_recursively(false)
-        .parse_callbacks(Box::new(BindgenCallback))
+        .parse_callbacks(Box::new(callback))
         .generate_comments(true);
 
-    if host != target {
-        builder = builder.clang_arg(format!("--target={}", rust_to_llvm_target()[target]))
+    builder = builder.raw_line(header.provenance());
+    for line in RAW_LINES {
+        builder = builder.raw_line(*line);
+    }
+    for prefix in ALLOWLIST {
+        builder = builder.allowlist_item(format!("{prefix}.*"));
+    }
+    if let Some(stub_include_dir) = stub_include_dir {
+        // `-ffreestanding` makes the compiler's own <stdint.h> define the types itself
+        // instead of deferring to the C library headers, which `-nostdlibinc` hides.
+        builder = builder.clang_args([
+            "-ffreestanding".to_owned(),
+            "-nostdlibinc".to_owned(),
+            format!("-I{}", stub_include_dir.display()),
+        ]);
     }
     let binding = builder.generate()?;
-    Ok(binding)
+    let included = std::mem::take(&mut *included.lock().unwrap());
+    Ok((binding, included))
 }
@@ -0,0 +1,114 @@
+//! Where the header comes from and how it is compiled, which environment variables can change.
+
+use crate::{api_version, WSL_PLUGIN_API_HEADER_FILE_NAME};
+use sha2::{Digest, Sha256};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Path of a `WslPluginApi.h` to use instead of the vendored one, such as a prerelease.
+pub(crate) const HEADER_ENV_VAR: &str = "WSLPLUGINAPI_SYS_HEADER";
+/// Extra include directories for clang, separated like `PATH`.
+pub(crate) const INCLUDE_DIRS_ENV_VAR: &str = "WSLPLUGINAPI_SYS_INCLUDE_DIRS";
+/// Extra `NAME` or `NAME=VALUE` definitions for the preprocessor, separated by whitespace.
+pub(crate) const DEFINES_ENV_VAR: &str = "WSLPLUGINAPI_SYS_DEFINES";
+
+#[derive(Debug)]
+pub(crate) struct HeaderSource {
+    pub(crate) path: PathBuf,
+    /// NuGet version of the vendored header, `None` for an overriding header.
+    pub(crate) version: Option<String>,
+    /// Hexadecimal SHA-256 of the header contents.
+    pub(crate) sha256: String,
+    pub(crate) include_dirs: Vec<PathBuf>,
+    pub(crate) defines: Vec<String>,
+}
+
+impl HeaderSource {
+    /// Resolves the header of `api_version` or its override, tracking the variables involved.
+    pub(crate) fn from_env(
+        manifest_dir: &Path,
+        api_version: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        for var in [HEADER_ENV_VAR, INCLUDE_DIRS_ENV_VAR, DEFINES_ENV_VAR] {
+            println!("cargo:rerun-if-env-changed={var}");
+        }
+        let (path, version) = match env::var_os(HEADER_ENV_VAR) {
+            Some(path) => (PathBuf::from(path), None),
+            None => (
+                api_version::vendored_dir(manifest_dir, api_version)?
+                    .join("include")
+                    .join(WSL_PLUGIN_API_HEADER_FILE_NAME),
+                Some(api_version.to_owned()),
+            ),
+        };
+        println!("cargo:rerun-if-changed={}", path.display());
+        let contents = fs::read(&path)
+            .map_err(|error| format!("Cannot read the header {}: {error}", path.display()))?;
+        let sha256 = sha256_hex(&contents);
+        let include_dirs = env::var_os(INCLUDE_DIRS_ENV_VAR)
+            .map(|dirs| env::split_paths(&dirs).collect())
+            .unwrap_or_default();
+        let defines = env::var(DEFINES_ENV_VAR)
+            .map(|defines| defines.split_whitespace().map(str::to_owned).collect())
+            .unwrap_or_default();
+        Ok(Self {
+            path,
+            version,
+            sha256,
+            include_dirs,
+            defines,
+        })
+    }
+
+    /// Comment written at the top of the generated bindings, so a mismatch between the
+    /// bindings and the header shows up in diffs.
+    #[cfg(any(feature = "bindgen", feature = "header-parser"))]
+    pub(crate) fn provenance(&self) -> String {
+        let origin = match &self.version {
+            Some(version) => format!("Microsoft.WSL.PluginApi {version}"),
+            None => format!("{HEADER_ENV_VAR} override"),
+        };
+        format!(
+            "// {WSL_PLUGIN_API_HEADER_FILE_NAME} of {origin}, sha256 {}",
+            self.sha256
+        )
+    }
+
+    /// Names made defined by [`DEFINES_ENV_VAR`].
+    #[cfg(feature = "header-parser")]
+    pub(crate) fn defined_names(&self) -> impl Iterator<Item = &str> {
+        self.defines.iter().map(|define| {
+            define
+                .split_once('=')
+                .map_or(define.as_str(), |(name, _)| name)
+        })
+    }
+
+    /// Include directories and definitions as clang arguments.
+    #[cfg(feature = "bindgen")]
+    pub(crate) fn clang_args(&self) -> Vec<String> {
+        self.include_dirs
+            .iter()
+            .map(|dir| format!("-I{}", dir.display()))
+            .chain(self.defines.iter().map(|define| format!("-D{define}")))
+            .collect()
+    }
+}
+
+/// Hashes `contents` with CRLF line endings read as LF, so the hash does not depend on how the
+/// header was checked out.
+pub(crate) fn sha256_hex(contents: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    let mut rest = contents;
+    while let Some(index) = rest.windows(2).position(|pair| pair == b"\r\n") {
+        hasher.update(&rest[..index]);
+        rest = &rest[index + 1..];
+    }
+    hasher.update(rest);
+    hasher
+        .finalize()
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
@@ -0,0 +1,29 @@
+/*
+ * Minimal stand-in for <Windows.h>, declaring only what WslPluginApi.h uses.
+ *
+ * Used by the `bindgen` feature so the bindings can be generated without a Windows SDK or
+ * MinGW header tree. Only the names matter: the generated bindings refer to the `windows`
+ * crate types of the same name, and layouts are checked against the real ones by the
+ * generated layout tests.
+ */
+#pragma once
+
+#include <stddef.h>
+#include <stdint.h>
+
+typedef int BOOL;
+typedef uint32_t DWORD;
+typedef int32_t HRESULT;
+typedef void *HANDLE;
+typedef void *PSID;
+typedef const char *LPCSTR;
+typedef const wchar_t *LPCWSTR;
+typedef uintptr_t SOCKET;
+
+typedef struct _GUID
+{
+    uint32_t Data1;
+    uint16_t Data2;
+    uint16_t Data3;
+    uint8_t Data4[8];
+} GUID;
@@ -1,34 +1,186 @@
+#[cfg(feature = "bindgen")]
 extern crate bindgen;
+mod api_version;
+#[cfg(feature = "bindgen")]
+mod bindings_cache;
+#[cfg(any(feature = "bindgen", feature = "header-parser"))]
+mod bindings_options;
+mod header_macros;
+#[cfg(feature = "header-parser")]
+mod header_parser;
+#[cfg(feature = "bindgen")]
 mod header_processing;
+mod header_source;
+mod pregenerated;
+mod target;
 use constcat::concat;
 use std::env;
 use std::path::PathBuf;
 const WSL_PLUGIN_API_FILE_BASE_NAME: &str = "WslPluginApi";
 const WSL_PLUGIN_API_HEADER_FILE_NAME: &str = concat!(WSL_PLUGIN_API_FILE_BASE_NAME, ".h");
-const WSL_PLUGIN_API_OUTPUT_FILE_NAME: &str = concat!(WSL_PLUGIN_API_FILE_BASE_NAME, ".rs");
+const WSL_PLUGIN_API_MACROS_OUTPUT_FILE_NAME: &str =
+    concat!(WSL_PLUGIN_API_FILE_BASE_NAME, "_macros.rs");
+#[cfg(feature = "header-parser")]
+const WSL_PLUGIN_API_PARSER_OUTPUT_FILE_NAME: &str =
+    concat!(WSL_PLUGIN_API_FILE_BASE_NAME, "_parser.rs");
+/// Set to generate the bindings against the system `Windows.h` instead of the bundled stub.
+#[cfg(feature = "bindgen")]
+const SYSTEM_HEADERS_ENV_VAR: &str = "WSLPLUGINAPI_SYS_SYSTEM_HEADERS";
+/// Names the clang the ABI conformance tests check the bindings with; they are ignored without it.
+const ABI_CLANG_ENV_VAR: &str = "WSLPLUGINAPI_SYS_CLANG";
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    println!("cargo:rerun-if-changed=build.rs");
-    let host = env::var("HOST")?;
-    let target = env::var("TARGET")?;
-    let out_path: PathBuf = env::var("OUT_DIR")?.into();
-
+    println!("cargo:rerun-if-changed=build/main.rs");
     let manifest_dir =
         PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is not set"));
-    let header_file_path = manifest_dir
-        .join("third_party/Microsoft.WSL.PluginApi/include")
-        .join(WSL_PLUGIN_API_HEADER_FILE_NAME);
-    println!("cargo:rerun-if-changed={}", header_file_path.display());
+    let target_arch = env::var("CARGO_CFG_TARGET_ARCH")?;
+    println!(
+        "cargo:rerun-if-env-changed={}",
+        target::HOST_TESTING_ENV_VAR
+    );
+    // Resolved whatever the generator to reject unsupported targets early.
+    target::clang_target(
+        &target_arch,
+        &env::var("CARGO_CFG_TARGET_OS")?,
+        &env::var("CARGO_CFG_TARGET_ENV").unwrap_or_default(),
+        env::var_os(target::HOST_TESTING_ENV_VAR).is_some(),
+    )?;
+    println!("cargo:rustc-check-cfg=cfg(wslpluginapi_sys_clang)");
+    println!("cargo:rerun-if-env-changed={ABI_CLANG_ENV_VAR}");
+    if env::var_os(ABI_CLANG_ENV_VAR).is_some() {
+        println!("cargo:rustc-cfg=wslpluginapi_sys_clang");
+    }
+    let api_versions = api_version::vendored(&manifest_dir)?;
+    let api_version = api_version::selected(&api_versions)?;
+    println!("cargo:rustc-env=WSL_PLUGIN_API_VERSION={api_version}");
+    api_version::emit(&api_versions, api_version);
+    let pregenerated_path = pregenerated::bindings_path(&manifest_dir, api_version, &target_arch)?;
+    println!("cargo:rerun-if-changed={}", pregenerated_path.display());
+    // Lets the tests compare generated bindings with the checked-in ones.
+    println!(
+        "cargo:rustc-env=WSL_PLUGIN_API_PREGENERATED_FILE_PATH={}",
+        pregenerated_path.display()
+    );
 
-    if !header_file_path.exists() {
-        return Err(format!("Header file does not exist: {:?}", header_file_path).into());
+    let out_path: PathBuf = env::var("OUT_DIR")?.into();
+    let header = header_source::HeaderSource::from_env(&manifest_dir, api_version)?;
+    println!(
+        "cargo:rustc-env=WSL_PLUGIN_API_HEADER_SHA256={}",
+        header.sha256
+    );
+    #[cfg(not(any(feature = "bindgen", feature = "header-parser")))]
+    if header.version.is_none() || !header.include_dirs.is_empty() || !header.defines.is_empty() {
+        return Err(format!(
+            "{}, {} and {} need the `bindgen` or `header-parser` feature, the checked-in \
+             bindings come from the vendored header",
+            header_source::HEADER_ENV_VAR,
+            header_source::INCLUDE_DIRS_ENV_VAR,
+            header_source::DEFINES_ENV_VAR
+        )
+        .into());
+    }
+    #[cfg(all(feature = "header-parser", not(feature = "bindgen")))]
+    if !header.include_dirs.is_empty() {
+        println!(
+            "cargo:warning={} is ignored by the header parser, which does not follow includes",
+            header_source::INCLUDE_DIRS_ENV_VAR
+        );
     }
-    let out_file = out_path.join(WSL_PLUGIN_API_OUTPUT_FILE_NAME);
-    let api_header = header_processing::process(header_file_path, host, target)?;
-    api_header.write_to_file(&out_file)?;
+
+    let macros_file = out_path.join(WSL_PLUGIN_API_MACROS_OUTPUT_FILE_NAME);
+    header_macros::generate(&header.path)?.write_to_file(&macros_file)?;
+    println!(
+        "cargo:rustc-env=WSL_PLUGIN_API_MACROS_FILE_PATH={}",
+        macros_file.display()
+    );
+
+    #[cfg(feature = "header-parser")]
+    let parser_output = {
+        let pointer_width: usize = env::var("CARGO_CFG_TARGET_POINTER_WIDTH")?.parse()?;
+        let out_file = out_path.join(WSL_PLUGIN_API_PARSER_OUTPUT_FILE_NAME);
+        header_parser::generate(&header, pointer_width)?.write_to_file(&out_file)?;
+        println!(
+            "cargo:rustc-env=WSL_PLUGIN_API_PARSER_OUTPUT_FILE_PATH={}",
+            out_file.display()
+        );
+        Some(out_file)
+    };
+    #[cfg(not(feature = "header-parser"))]
+    let parser_output: Option<PathBuf> = None;
+
+    #[cfg(feature = "bindgen")]
+    let bindgen_output = {
+        println!("cargo:rerun-if-env-changed={SYSTEM_HEADERS_ENV_VAR}");
+        let stub_include_dir = manifest_dir.join("build/include");
+        let stub_include_dir = match env::var_os(SYSTEM_HEADERS_ENV_VAR) {
+            Some(_) => None,
+            None => {
+                println!("cargo:rerun-if-changed={}", stub_include_dir.display());
+                Some(stub_include_dir.as_path())
+            }
+        };
+        println!(
+            "cargo:rerun-if-env-changed={}",
+            pregenerated::UPDATE_ENV_VAR
+        );
+        let update = env::var_os(pregenerated::UPDATE_ENV_VAR).is_some();
+        if update && header.version.is_none() {
+            println!(
+                "cargo:warning=Not updating the checked-in bindings from the {} header",
+                header_source::HEADER_ENV_VAR
+            );
+        }
+        // The bindings of every architecture are generated, not only those of the target, so
+        // that the tests check all the checked-in files whatever the target.
+        let bindgen_dir = out_path.join("bindgen");
+        std::fs::create_dir_all(&bindgen_dir)?;
+        let cache_dir = bindings_cache::dir();
+        for (arch, file_name) in pregenerated::files() {
+            let bindings_target =
+                target::bindings_clang_target(&target::clang_target(arch, "windows", "", false)?);
+            let out_file = bindgen_dir.join(file_name);
+            let cache_key = bindings_cache::key(&header, &bindings_target, stub_include_dir)?;
+            bindings_cache::write_bindings(cache_dir.as_deref(), &cache_key, &out_file, || {
+                header_processing::process(&header, &bindings_target, stub_include_dir)
+            })?;
+            if update && header.version.is_some() {
+                let pregenerated_path =
+                    pregenerated::bindings_path(&manifest_dir, api_version, arch)?;
+                if let Some(dir) = pregenerated_path.parent() {
+                    std::fs::create_dir_all(dir)?;
+                }
+                std::fs::copy(&out_file, &pregenerated_path)?;
+            }
+        }
+        println!(
+            "cargo:rustc-env=WSL_PLUGIN_API_BINDGEN_DIR={}",
+            bindgen_dir.display()
+        );
+        let out_file = bindgen_dir.join(
+            pregenerated_path
+                .file_name()
+                .expect("the checked-in bindings path names a file"),
+        );
+        println!(
+            "cargo:rustc-env=WSL_PLUGIN_API_BINDGEN_FILE_PATH={}",
+            out_file.display()
+        );
+        Some(out_file)
+    };
+    #[cfg(not(feature = "bindgen"))]
+    let bindgen_output: Option<PathBuf> = None;
+
+    // bindgen stays the reference when both generators are enabled.
+    let bindings_path = match bindgen_output.or(parser_output) {
+        Some(generated) => generated,
+        None => {
+            pregenerated::check_exists(&pregenerated_path)?;
+            pregenerated_path
+        }
+    };
     println!(
         "cargo:rustc-env=WSL_PLUGIN_API_BINDGEN_OUTPUT_FILE_PATH={}",
-        out_file.display()
+        bindings_path.display()
     );
     Ok(())
 }
@@ -0,0 +1,70 @@
+use std::path::{Path, PathBuf};
+
+/// Set together with the `bindgen` feature to overwrite the checked-in bindings with the
+/// generated ones.
+#[cfg(feature = "bindgen")]
+pub(crate) const UPDATE_ENV_VAR: &str = "WSLPLUGINAPI_SYS_UPDATE_BINDINGS";
+
+/// Checked-in bindings for each supported `target_arch`.
+///
+/// The header only depends on the pointer width and alignment rules, so the `gnu`, `gnullvm`
+/// and `msvc` targets of an architecture share the same file. ARM64EC code uses the x64 data
+/// layout to interoperate with it.
+const PREGENERATED_BINDINGS: &[(&str, &str)] = &[
+    ("x86_64", "x86_64.rs"),
+    ("arm64ec", "x86_64.rs"),
+    ("x86", "i686.rs"),
+    ("aarch64", "aarch64.rs"),
+];
+
+/// Returns each checked-in bindings file once, with the first `target_arch` using it.
+#[cfg(feature = "bindgen")]
+pub(crate) fn files() -> impl Iterator<Item = (&'static str, &'static str)> {
+    PREGENERATED_BINDINGS
+        .iter()
+        .enumerate()
+        .filter(|(index, (_, file_name))| {
+            !PREGENERATED_BINDINGS[..*index]
+                .iter()
+                .any(|(_, previous)| previous == file_name)
+        })
+        .map(|(_, entry)| *entry)
+}
+
+/// Returns the path of the checked-in bindings of the API `version` for `target_arch`.
+///
+/// The file may not exist yet for a newly vendored version; [`check_exists`] reports that.
+pub(crate) fn bindings_path(
+    manifest_dir: &Path,
+    version: &str,
+    target_arch: &str,
+) -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let (_, file_name) = PREGENERATED_BINDINGS
+        .iter()
+        .find(|(arch, _)| *arch == target_arch)
+        .ok_or_else(|| {
+            let supported: Vec<_> = PREGENERATED_BINDINGS
+                .iter()
+                .map(|(arch, _)| *arch)
+                .collect();
+            format!(
+                "No pre-generated bindings for target architecture {target_arch:?} (supported: {})",
+                supported.join(", ")
+            )
+        })?;
+    Ok(manifest_dir.join("bindings").join(version).join(file_name))
+}
+
+/// Fails with a hint on how to generate the checked-in bindings at `path` when they are missing.
+pub(crate) fn check_exists(path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    if path.is_file() {
+        Ok(())
+    } else {
+        Err(format!(
+            "No pre-generated bindings at {}, build once with the `bindgen` feature and \
+             WSLPLUGINAPI_SYS_UPDATE_BINDINGS=1 to create them",
+            path.display()
+        )
+        .into())
+    }
+}
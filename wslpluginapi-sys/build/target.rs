@@ -0,0 +1,124 @@
+//! Resolution of the clang target matching the Cargo target.
+//!
+//! The triple is derived from the `CARGO_CFG_TARGET_*` values instead of a list of Rust
+//! triples, so new Windows targets such as `arm64ec-pc-windows-msvc` need no change here. The
+//! file is also compiled into the library tests, which is where its unit tests run.
+
+/// Set to build for a non-Windows target, typically the host when running unit tests, with the
+/// layouts of the Windows target of the same architecture.
+pub(crate) const HOST_TESTING_ENV_VAR: &str = "WSLPLUGINAPI_SYS_HOST_TESTING";
+
+/// Returns the clang target for the Cargo target described by its `target_arch`, `target_os`
+/// and `target_env` cfg values.
+///
+/// Targets other than Windows are rejected unless `host_testing` is set, in which case the
+/// Windows target of the same architecture is returned.
+pub(crate) fn clang_target(
+    arch: &str,
+    os: &str,
+    env: &str,
+    host_testing: bool,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if arch.is_empty() {
+        return Err("The target architecture is not set".into());
+    }
+    // Rust names 32-bit x86 `x86` whatever the exact CPU, clang wants a CPU name.
+    let arch = match arch {
+        "x86" => "i686",
+        arch => arch,
+    };
+    let env = match os {
+        "windows" => env,
+        _ if host_testing => "gnu",
+        os => {
+            return Err(format!(
+                "The WSL Plugin API only exists on Windows, but the target OS is {os:?}; set \
+                 {HOST_TESTING_ENV_VAR}=1 to build for this target with the layouts of the \
+                 {arch} Windows target, for instance to run unit tests on the host"
+            )
+            .into())
+        }
+    };
+    Ok(match env {
+        "gnu" => format!("{arch}-w64-mingw32"),
+        "msvc" | "" => format!("{arch}-pc-windows-msvc"),
+        env => format!("{arch}-pc-windows-{env}"),
+    })
+}
+
+/// Returns the clang target the bindings of a Cargo target are generated for.
+///
+/// The checked-in bindings of an architecture are shared by its `gnu`, `gnullvm` and `msvc`
+/// targets, so they are always generated for `msvc`: with the mingw target, clang gives the
+/// enums without negative values an `unsigned int` underlying type instead of `int`. The ABI
+/// tests check the shared layouts against every target.
+#[cfg_attr(not(feature = "bindgen"), allow(dead_code))]
+pub(crate) fn bindings_clang_target(clang_target: &str) -> String {
+    let arch = clang_target.split('-').next().unwrap_or(clang_target);
+    format!("{arch}-pc-windows-msvc")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_targets() {
+        for (arch, env, expected) in [
+            ("x86_64", "gnu", "x86_64-w64-mingw32"),
+            ("x86", "gnu", "i686-w64-mingw32"),
+            ("aarch64", "gnu", "aarch64-w64-mingw32"),
+            ("x86_64", "msvc", "x86_64-pc-windows-msvc"),
+            ("x86", "msvc", "i686-pc-windows-msvc"),
+            ("aarch64", "msvc", "aarch64-pc-windows-msvc"),
+            ("arm64ec", "msvc", "arm64ec-pc-windows-msvc"),
+        ] {
+            assert_eq!(
+                clang_target(arch, "windows", env, false).unwrap(),
+                expected,
+                "{arch} {env}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_unknown_windows_targets() {
+        assert_eq!(
+            clang_target("riscv64", "windows", "msvc", false).unwrap(),
+            "riscv64-pc-windows-msvc"
+        );
+        assert_eq!(
+            clang_target("x86_64", "windows", "itanium", false).unwrap(),
+            "x86_64-pc-windows-itanium"
+        );
+        assert!(clang_target("", "windows", "msvc", false).is_err());
+    }
+
+    #[test]
+    fn test_bindings_targets() {
+        for (env, expected) in [
+            ("gnu", "x86_64-pc-windows-msvc"),
+            ("gnullvm", "x86_64-pc-windows-msvc"),
+            ("msvc", "x86_64-pc-windows-msvc"),
+        ] {
+            let target = clang_target("x86_64", "windows", env, false).unwrap();
+            assert_eq!(bindings_clang_target(&target), expected);
+        }
+        let target = clang_target("x86", "linux", "gnu", true).unwrap();
+        assert_eq!(bindings_clang_target(&target), "i686-pc-windows-msvc");
+    }
+
+    #[test]
+    fn test_non_windows_targets() {
+        let error = clang_target("x86_64", "linux", "gnu", false).unwrap_err();
+        assert!(error.to_string().contains(HOST_TESTING_ENV_VAR));
+        assert_eq!(
+            clang_target("x86_64", "linux", "gnu", true).unwrap(),
+            "x86_64-w64-mingw32"
+        );
+        assert_eq!(
+            clang_target("aarch64", "macos", "", true).unwrap(),
+            "aarch64-w64-mingw32"
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Checks the checked-in bindings of every architecture against the layouts and calling
+//! conventions clang gives the header on each Windows target.
+//!
+//! The bindings assert their own layouts, so rustc checks the Rust side when building for an
+//! architecture; these tests turn the same numbers into C assertions for clang. They need a
+//! `clang` able to target Windows, named by `WSLPLUGINAPI_SYS_CLANG`, and are ignored when that
+//! variable is not set.
+//!
+//! Each target is compiled against the system `windows.h` (the Windows SDK or MinGW, with
+//! clang's `C_INCLUDE_PATH` for other locations) when clang finds one for it, and against the
+//! stub of the build script otherwise. The targets listed, comma-separated, in
+//! `WSLPLUGINAPI_SYS_ABI_SYSTEM_HEADERS` fail instead of falling back to the stub.
+
+use crate::build_target::clang_target;
+use crate::WSL_PLUGIN_API_VERSION_STR;
+use std::fmt::Write as _;
+use std::io::{self, Write as _};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+const CLANG_ENV_VAR: &str = "WSLPLUGINAPI_SYS_CLANG";
+const SYSTEM_HEADERS_ENV_VAR: &str = "WSLPLUGINAPI_SYS_ABI_SYSTEM_HEADERS";
+
+/// Supported Rust targets with their `target_arch` and `target_env`, and their checked-in
+/// bindings.
+const TARGETS: &[(&str, &str, &str, &str)] = &[
+    ("x86_64-pc-windows-gnu", "x86_64", "gnu", "x86_64.rs"),
+    ("i686-pc-windows-gnu", "x86", "gnu", "i686.rs"),
+    ("x86_64-pc-windows-gnullvm", "x86_64", "gnu", "x86_64.rs"),
+    ("i686-pc-windows-gnullvm", "x86", "gnu", "i686.rs"),
+    ("aarch64-pc-windows-gnullvm", "aarch64", "gnu", "aarch64.rs"),
+    ("x86_64-pc-windows-msvc", "x86_64", "msvc", "x86_64.rs"),
+    ("i686-pc-windows-msvc", "x86", "msvc", "i686.rs"),
+    ("aarch64-pc-windows-msvc", "aarch64", "msvc", "aarch64.rs"),
+    ("arm64ec-pc-windows-msvc", "arm64ec", "msvc", "x86_64.rs"),
+];
+
+#[derive(Debug, PartialEq, Eq)]
+enum LayoutAssertion {
+    Size {
+        ty: String,
+        value: usize,
+    },
+    Alignment {
+        ty: String,
+        value: usize,
+    },
+    Offset {
+        ty: String,
+        field: String,
+        value: usize,
+    },
+}
+
+/// Extracts the layout assertions bindgen writes as `["Size of T"][size_of::<T>() - 12usize];`.
+fn layout_assertions(bindings: &str) -> Vec<LayoutAssertion> {
+    let compact: String = bindings.split_whitespace().collect();
+    compact
+        .split("[\"")
+        .skip(1)
+        .filter_map(|chunk| {
+            let (label, rest) = chunk.split_once("\"]")?;
+            let expression = rest.strip_prefix('[')?.split_once(']')?.0;
+            let value = expression
+                .rsplit_once('-')?
+                .1
+                .strip_suffix("usize")?
+                .parse()
+                .ok()?;
+            if let Some(ty) = label.strip_prefix("Sizeof") {
+                Some(LayoutAssertion::Size {
+                    ty: ty.to_owned(),
+                    value,
+                })
+            } else if let Some(ty) = label.strip_prefix("Alignmentof") {
+                Some(LayoutAssertion::Alignment {
+                    ty: ty.to_owned(),
+                    value,
+                })
+            } else {
+                let (ty, field) = label.strip_prefix("Offsetoffield:")?.split_once("::")?;
+                Some(LayoutAssertion::Offset {
+                    ty: ty.to_owned(),
+                    field: field.to_owned(),
+                    value,
+                })
+            }
+        })
+        .collect()
+}
+
+/// Returns the callback typedefs of the bindings with the ABI of their function pointer.
+fn callback_abis(bindings: &str) -> Vec<(String, String)> {
+    bindings
+        .split("pub type ")
+        .skip(1)
+        .filter_map(|item| {
+            let (name, definition) = item.split_once('=')?;
+            let definition = definition.split_once(';')?.0;
+            let abi = definition.split_once("extern \"")?.1.split_once('"')?.0;
+            Some((name.trim().to_owned(), abi.to_owned()))
+        })
+        .collect()
+}
+
+/// The `windows.h` a target is checked against.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum WindowsHeaders {
+    /// The Windows SDK or MinGW headers clang finds for the target.
+    System,
+    /// The stub in `build/include`, declaring only what the header uses.
+    Stub,
+}
+
+fn vendored_header_dir() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("third_party/Microsoft.WSL.PluginApi")
+        .join(WSL_PLUGIN_API_VERSION_STR)
+        .join("include")
+}
+
+/// Returns the preamble including the header. With system headers, the header is inlined
+/// with its `Windows.h` include lowercased, the only spelling MinGW provides.
+fn header_preamble(headers: WindowsHeaders) -> String {
+    match headers {
+        WindowsHeaders::Stub => "#include <stddef.h>\n#include <WslPluginApi.h>\n".to_owned(),
+        WindowsHeaders::System => {
+            let header = std::fs::read_to_string(vendored_header_dir().join("WslPluginApi.h"))
+                .unwrap()
+                .replace("#include <Windows.h>", "#include <windows.h>");
+            format!("#include <stddef.h>\n{header}\n")
+        }
+    }
+}
+
+/// Builds a C translation unit asserting the layouts and, on 32-bit x86 where it matters, the
+/// calling conventions of `bindings`.
+fn c_assertions(bindings: &str, x86: bool, headers: WindowsHeaders) -> String {
+    let mut source = header_preamble(headers);
+    for assertion in layout_assertions(bindings) {
+        let (expression, value) = match &assertion {
+            LayoutAssertion::Size { ty, value } => (format!("sizeof(struct {ty})"), value),
+            LayoutAssertion::Alignment { ty, value } => (format!("_Alignof(struct {ty})"), value),
+            LayoutAssertion::Offset { ty, field, value } => {
+                (format!("offsetof(struct {ty}, {field})"), value)
+            }
+        };
+        writeln!(
+            source,
+            "_Static_assert({expression} == {value}, \"{expression} is not {value}\");"
+        )
+        .unwrap();
+    }
+    if x86 {
+        for (name, abi) in callback_abis(bindings) {
+            let convention = match abi.as_str() {
+                "C" => "cdecl",
+                "stdcall" | "system" => "stdcall",
+                abi => panic!("unexpected ABI {abi:?} for {name}"),
+            };
+            // Redeclaring a function with another convention than the header's is an error.
+            writeln!(
+                source,
+                "extern __typeof__(*({name})0) {name}_check;\n\
+                 extern __typeof__(*({name})0) __attribute__(({convention})) {name}_check;"
+            )
+            .unwrap();
+        }
+    }
+    source
+}
+
+fn clang() -> PathBuf {
+    std::env::var_os(CLANG_ENV_VAR).map_or_else(|| "clang".into(), PathBuf::from)
+}
+
+/// Returns `true` if `rust_target` must be checked against the system headers.
+fn requires_system_headers(rust_target: &str) -> bool {
+    std::env::var(SYSTEM_HEADERS_ENV_VAR).is_ok_and(|targets| {
+        targets
+            .split(',')
+            .any(|target| target.trim() == rust_target)
+    })
+}
+
+/// Picks the headers to check `clang_target` against, returning `None` when clang is not
+/// installed and was not explicitly requested.
+fn windows_headers(rust_target: &str, clang_target: &str) -> io::Result<WindowsHeaders> {
+    let (found, diagnostics) = check_with_clang(
+        clang_target,
+        WindowsHeaders::System,
+        "#include <windows.h>\n",
+    )?;
+    if found {
+        Ok(WindowsHeaders::System)
+    } else if requires_system_headers(rust_target) {
+        Err(io::Error::other(format!(
+            "no system windows.h for {rust_target}, which {SYSTEM_HEADERS_ENV_VAR} \
+             requires:\n{diagnostics}"
+        )))
+    } else {
+        Ok(WindowsHeaders::Stub)
+    }
+}
+
+/// Compiles `source` for `target`, returning whether it succeeded and the diagnostics.
+fn check_with_clang(
+    target: &str,
+    headers: WindowsHeaders,
+    source: &str,
+) -> io::Result<(bool, String)> {
+    let mut command = Command::new(clang());
+    command.arg(format!("--target={target}")).args([
+        "-fsyntax-only",
+        "-std=gnu11",
+        "-Werror",
+        "-x",
+        "c",
+        "-",
+    ]);
+    if headers == WindowsHeaders::Stub {
+        command
+            .args(["-ffreestanding", "-nostdlibinc", "-I"])
+            .arg(Path::new(env!("CARGO_MANIFEST_DIR")).join("build/include"))
+            .arg("-I")
+            .arg(vendored_header_dir());
+    }
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child.stdin.take().unwrap().write_all(source.as_bytes())?;
+    let output = child.wait_with_output()?;
+    Ok((
+        output.status.success(),
+        String::from_utf8_lossy(&output.stderr).into_owned(),
+    ))
+}
+
+#[test]
+fn test_layout_assertions() {
+    let bindings = include_str!(env!("WSL_PLUGIN_API_PREGENERATED_FILE_PATH"));
+    let assertions = layout_assertions(bindings);
+    let size = std::mem::size_of::<crate::WSLVersion>();
+    assert!(assertions.contains(&LayoutAssertion::Size {
+        ty: "WSLVersion".into(),
+        value: size,
+    }));
+    assert!(assertions.contains(&LayoutAssertion::Offset {
+        ty: "WSLPluginAPIV1".into(),
+        field: "PluginError".into(),
+        value: std::mem::offset_of!(crate::WSLPluginAPIV1, PluginError),
+    }));
+    let mut types = vec![
+        "WSLPluginAPIV1",
+        "WSLPluginHooksV1",
+        "WSLDistributionInformation",
+        "WSLSessionInformation",
+    ];
+    if cfg!(wsl_plugin_api_ge_2_1_2) {
+        types.push("WslOfflineDistributionInformation");
+    }
+    for ty in types {
+        assert!(
+            assertions
+                .iter()
+                .any(|assertion| matches!(assertion, LayoutAssertion::Alignment { ty: name, .. } if name == ty)),
+            "no layout assertion for {ty}"
+        );
+    }
+    assert!(callback_abis(bindings)
+        .iter()
+        .any(|(name, abi)| name == "WSLPluginAPI_OnVMStarted" && abi == "C"));
+}
+
+#[test]
+#[cfg_attr(
+    not(wslpluginapi_sys_clang),
+    ignore = "set WSLPLUGINAPI_SYS_CLANG to a clang able to target Windows"
+)]
+fn test_layouts_match_clang() {
+    let bindings_dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("bindings")
+        .join(WSL_PLUGIN_API_VERSION_STR);
+    let mut failures = Vec::new();
+    for (rust_target, arch, env, file_name) in TARGETS {
+        let clang_target = clang_target(arch, "windows", env, false).unwrap();
+        let bindings = std::fs::read_to_string(bindings_dir.join(file_name)).unwrap();
+        let headers = match windows_headers(rust_target, &clang_target) {
+            Ok(headers) => headers,
+            Err(error) => {
+                failures.push(error.to_string());
+                continue;
+            }
+        };
+        let origin = match headers {
+            WindowsHeaders::System => "system",
+            WindowsHeaders::Stub => "stub",
+        };
+        eprintln!("checking {rust_target} against the {origin} windows.h");
+        let source = c_assertions(&bindings, *arch == "x86", headers);
+        let (success, diagnostics) = check_with_clang(&clang_target, headers, &source)
+            .unwrap_or_else(|error| panic!("cannot run {}: {error}", clang().display()));
+        if !success {
+            failures.push(format!("{rust_target} ({file_name}):\n{diagnostics}"));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
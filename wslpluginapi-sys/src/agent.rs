@@ -0,0 +1,139 @@
+use crate::windows::core::GUID;
+use crate::{
+    execute_binary_in_distribution, ProcessStream, WSLPluginAPIV1, WSLSessionId, WSLVersion,
+};
+use std::fmt;
+use wslpluginapi_agent::{Client, RpcError, WslVersion};
+
+/// Connection to a `wslpluginapi-agent` running in a distribution.
+pub type AgentClient = Client<ProcessStream>;
+
+/// Error returned by [`launch_agent`].
+#[derive(Debug)]
+pub enum AgentLaunchError {
+    /// `ExecuteBinaryInDistribution` failed.
+    Launch(crate::windows::core::Error),
+    /// The agent started but the protocol handshake failed.
+    Handshake(RpcError),
+}
+
+impl fmt::Display for AgentLaunchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Launch(error) => write!(f, "failed to start the agent: {error}"),
+            Self::Handshake(error) => write!(f, "failed to connect to the agent: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for AgentLaunchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Launch(error) => Some(error),
+            Self::Handshake(error) => Some(error),
+        }
+    }
+}
+
+/// Starts the agent binary at `agent_path` inside a distribution and connects to it.
+///
+/// The agent keeps running until the returned client is dropped or the distribution stops.
+///
+/// # Safety
+///
+/// `api` must be the table received at the plugin entry point, and WSL must be at least 2.1.2.
+pub unsafe fn launch_agent(
+    api: &WSLPluginAPIV1,
+    session: WSLSessionId,
+    distribution: &GUID,
+    agent_path: &str,
+) -> Result<AgentClient, AgentLaunchError> {
+    let stream =
+        execute_binary_in_distribution(api, session, distribution, agent_path, &[agent_path])
+            .map_err(AgentLaunchError::Launch)?;
+    AgentClient::connect(stream).map_err(AgentLaunchError::Handshake)
+}
+
+impl From<WslVersion> for WSLVersion {
+    fn from(version: WslVersion) -> Self {
+        Self {
+            Major: version.major,
+            Minor: version.minor,
+            Revision: version.revision,
+        }
+    }
+}
+
+impl From<WSLVersion> for WslVersion {
+    fn from(version: WSLVersion) -> Self {
+        Self::new(version.Major, version.Minor, version.Revision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{api_table, socket_pair};
+    use crate::windows::core::{HRESULT, PCSTR};
+    use crate::windows::Win32::Foundation::{E_ACCESSDENIED, S_OK};
+    use crate::windows::Win32::Networking::WinSock::SOCKET;
+
+    /// Runs the agent server on a thread, as if the agent had been started in the distribution.
+    unsafe extern "C" fn agent_execute_binary_in_distribution(
+        _session: WSLSessionId,
+        _distribution: *const GUID,
+        _path: PCSTR,
+        _arguments: *mut PCSTR,
+        socket: *mut SOCKET,
+    ) -> HRESULT {
+        let (host, guest) = socket_pair();
+        std::thread::spawn(move || wslpluginapi_agent::agent_server().serve(guest));
+        *socket = host;
+        S_OK
+    }
+
+    unsafe extern "C" fn denied_execute_binary_in_distribution(
+        _session: WSLSessionId,
+        _distribution: *const GUID,
+        _path: PCSTR,
+        _arguments: *mut PCSTR,
+        _socket: *mut SOCKET,
+    ) -> HRESULT {
+        E_ACCESSDENIED
+    }
+
+    fn api(
+        execute_binary_in_distribution: crate::WSLPluginAPI_ExecuteBinaryInDistribution,
+    ) -> WSLPluginAPIV1 {
+        WSLPluginAPIV1 {
+            ExecuteBinaryInDistribution: execute_binary_in_distribution,
+            ..api_table(2, 4, 4)
+        }
+    }
+
+    #[test]
+    fn test_launch_agent() {
+        let api = api(Some(agent_execute_binary_in_distribution));
+        let mut client =
+            unsafe { launch_agent(&api, 1, &GUID::zeroed(), "/usr/libexec/wslpluginapi-agent") }
+                .unwrap();
+        assert_eq!(client.call("ping", b"payload").unwrap(), b"payload");
+        assert!(!client.call("version", b"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_launch_failure() {
+        let api = api(Some(denied_execute_binary_in_distribution));
+        let error = unsafe { launch_agent(&api, 1, &GUID::zeroed(), "/agent") }.unwrap_err();
+        assert!(
+            matches!(error, AgentLaunchError::Launch(ref error) if error.code() == E_ACCESSDENIED)
+        );
+    }
+
+    #[test]
+    fn test_version_conversion() {
+        let version: WSLVersion = "2.4.4".parse::<WslVersion>().unwrap().into();
+        assert_eq!((version.Major, version.Minor, version.Revision), (2, 4, 4));
+        assert_eq!(WslVersion::from(version), WslVersion::new(2, 4, 4));
+    }
+}
@@ -0,0 +1,83 @@
+use crate::WSLVersion;
+
+/// Version of the `Microsoft.WSL.PluginApi` header the bindings come from, such as `"2.4.4"`.
+///
+/// It is chosen with the `api-*` features and defaults to the latest vendored version.
+pub const WSL_PLUGIN_API_VERSION_STR: &str = env!("WSL_PLUGIN_API_VERSION");
+
+/// [`WSL_PLUGIN_API_VERSION_STR`] as a [`WSLVersion`].
+///
+/// Passing it to [`require_version`](crate::require_version) rejects a WSL older than the
+/// header the plugin was compiled against.
+pub const WSL_PLUGIN_API_VERSION: WSLVersion = parse_version(WSL_PLUGIN_API_VERSION_STR);
+
+/// SHA-256 of the header the bindings and macros were generated from, CRLF read as LF.
+///
+/// It differs from the vendored header's when `WSLPLUGINAPI_SYS_HEADER` overrides it.
+pub const WSL_PLUGIN_API_HEADER_SHA256: &str = env!("WSL_PLUGIN_API_HEADER_SHA256");
+
+const fn parse_version(text: &str) -> WSLVersion {
+    let bytes = text.as_bytes();
+    let mut parts = [0u32; 3];
+    let mut part = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'.' => {
+                part += 1;
+                assert!(part < parts.len(), "too many components in the API version");
+            }
+            digit @ b'0'..=b'9' => parts[part] = parts[part] * 10 + (digit - b'0') as u32,
+            _ => panic!("invalid character in the API version"),
+        }
+        i += 1;
+    }
+    assert!(
+        part == parts.len() - 1,
+        "the API version needs three components"
+    );
+    WSLVersion {
+        Major: parts[0],
+        Minor: parts[1],
+        Revision: parts[2],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version() {
+        let version = parse_version("2.10.3");
+        assert_eq!((version.Major, version.Minor, version.Revision), (2, 10, 3));
+    }
+
+    #[test]
+    fn test_api_version_matches_its_text() {
+        let version = WSL_PLUGIN_API_VERSION;
+        assert_eq!(
+            format!("{}.{}.{}", version.Major, version.Minor, version.Revision),
+            WSL_PLUGIN_API_VERSION_STR
+        );
+    }
+
+    #[test]
+    fn test_version_cfgs() {
+        let at_least = |major, minor, revision| {
+            WSL_PLUGIN_API_VERSION
+                >= WSLVersion {
+                    Major: major,
+                    Minor: minor,
+                    Revision: revision,
+                }
+        };
+        assert_eq!(cfg!(wsl_plugin_api_ge_2_0_5), at_least(2, 0, 5));
+        assert_eq!(cfg!(wsl_plugin_api_ge_2_1_2), at_least(2, 1, 2));
+        assert_eq!(cfg!(wsl_plugin_api_ge_2_4_4), at_least(2, 4, 4));
+        assert_eq!(
+            cfg!(wsl_plugin_api_2_4_4),
+            WSL_PLUGIN_API_VERSION_STR == "2.4.4"
+        );
+    }
+}
@@ -2,3 +2,90 @@
 #![allow(non_camel_case_types)]
 #![allow(non_snake_case)]
 include!(env!("WSL_PLUGIN_API_BINDGEN_OUTPUT_FILE_PATH"));
+
+#[cfg(test)]
+mod tests {
+    /// Ignores formatting, which depends on whether rustfmt was available to bindgen, and the
+    /// banner naming the generator.
+    #[cfg(any(feature = "bindgen", feature = "header-parser"))]
+    fn normalize(bindings: &str) -> String {
+        let compact: String = bindings
+            .trim_start()
+            .strip_prefix("/*")
+            .and_then(|rest| rest.split_once("*/"))
+            .map_or(bindings, |(_, rest)| rest)
+            .split_whitespace()
+            .collect();
+        // rustfmt adds trailing commas to the lists it splits over several lines.
+        compact
+            .replace(",)", ")")
+            .replace(",>", ">")
+            .replace(",}", "}")
+    }
+
+    // Catches a header update made without refreshing the checked-in bindings.
+    #[test]
+    fn test_pregenerated_bindings_record_the_vendored_header() {
+        use sha2::{Digest, Sha256};
+
+        let header = std::fs::read_to_string(
+            std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("third_party/Microsoft.WSL.PluginApi")
+                .join(crate::WSL_PLUGIN_API_VERSION_STR)
+                .join("include/WslPluginApi.h"),
+        )
+        .unwrap();
+        let sha256: String = Sha256::digest(header.replace("\r\n", "\n"))
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect();
+        let pregenerated = include_str!(env!("WSL_PLUGIN_API_PREGENERATED_FILE_PATH"));
+        assert!(
+            pregenerated.contains(&format!("sha256 {sha256}")),
+            "the checked-in bindings at {} were not generated from the vendored header",
+            env!("WSL_PLUGIN_API_PREGENERATED_FILE_PATH")
+        );
+    }
+
+    // The build script generates every architecture, so this checks all the checked-in files
+    // whatever the target.
+    #[cfg(feature = "bindgen")]
+    #[test]
+    fn test_pregenerated_bindings_are_up_to_date() {
+        let generated_dir = std::path::Path::new(env!("WSL_PLUGIN_API_BINDGEN_DIR"));
+        let pregenerated_dir = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("bindings")
+            .join(crate::WSL_PLUGIN_API_VERSION_STR);
+        let stale: Vec<_> = ["x86_64.rs", "i686.rs", "aarch64.rs"]
+            .into_iter()
+            .filter(|file_name| {
+                let read = |dir: &std::path::Path| std::fs::read_to_string(dir.join(file_name));
+                normalize(&read(generated_dir).unwrap())
+                    != normalize(&read(pregenerated_dir.as_path()).unwrap())
+            })
+            .collect();
+        assert!(
+            stale.is_empty(),
+            "the checked-in bindings {stale:?} in {} differ from the bindgen output, rebuild \
+             with the `bindgen` feature and WSLPLUGINAPI_SYS_UPDATE_BINDINGS=1 to update them",
+            pregenerated_dir.display()
+        );
+    }
+
+    // The checked-in bindings are bindgen output, so this holds without libclang too.
+    #[cfg(feature = "header-parser")]
+    #[test]
+    fn test_header_parser_matches_pregenerated_bindings() {
+        let parsed = include_str!(env!("WSL_PLUGIN_API_PARSER_OUTPUT_FILE_PATH"));
+        let pregenerated = include_str!(env!("WSL_PLUGIN_API_PREGENERATED_FILE_PATH"));
+        assert_eq!(normalize(parsed), normalize(pregenerated));
+    }
+
+    #[cfg(all(feature = "bindgen", feature = "header-parser"))]
+    #[test]
+    fn test_header_parser_matches_bindgen() {
+        let parsed = include_str!(env!("WSL_PLUGIN_API_PARSER_OUTPUT_FILE_PATH"));
+        let generated = include_str!(env!("WSL_PLUGIN_API_BINDGEN_FILE_PATH"));
+        assert_eq!(normalize(parsed), normalize(generated));
+    }
+}
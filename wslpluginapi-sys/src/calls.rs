@@ -0,0 +1,172 @@
+use crate::strings::to_wide_string;
+#[cfg(wsl_plugin_api_ge_2_1_2)]
+use crate::windows::core::GUID;
+use crate::windows::core::{Error, Result, HRESULT, PCSTR, PCWSTR};
+use crate::windows::Win32::Foundation::{E_INVALIDARG, E_NOTIMPL};
+use crate::windows::Win32::Networking::WinSock::SOCKET;
+use crate::{WSLPluginAPIV1, WSLSessionId};
+use std::ffi::CString;
+
+/// Stream connected to the standard input and output of a binary started by WSL.
+///
+/// On Windows this is the socket returned by WSL. Other hosts only exist to test plugins, and
+/// use a Unix stream whose file descriptor is passed as the `SOCKET` value.
+#[cfg(windows)]
+pub type ProcessStream = std::net::TcpStream;
+/// Stream connected to the standard input and output of a binary started by WSL.
+///
+/// On Windows this is the socket returned by WSL. Other hosts only exist to test plugins, and
+/// use a Unix stream whose file descriptor is passed as the `SOCKET` value.
+#[cfg(unix)]
+pub type ProcessStream = std::os::unix::net::UnixStream;
+
+/// Takes ownership of a socket returned by `ExecuteBinary` or `ExecuteBinaryInDistribution`.
+///
+/// # Safety
+///
+/// `socket` must be a valid, connected socket that is not owned by anything else.
+pub unsafe fn process_stream_from_socket(socket: SOCKET) -> ProcessStream {
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::{FromRawSocket, RawSocket};
+        ProcessStream::from_raw_socket(socket.0 as RawSocket)
+    }
+    #[cfg(unix)]
+    {
+        use std::os::fd::{FromRawFd, RawFd};
+        ProcessStream::from_raw_fd(socket.0 as RawFd)
+    }
+}
+
+/// Calls `WSLPluginAPI_MountFolder` with Rust strings.
+///
+/// # Safety
+///
+/// `api` must be the table received at the plugin entry point.
+pub unsafe fn mount_folder(
+    api: &WSLPluginAPIV1,
+    session: WSLSessionId,
+    windows_path: &str,
+    linux_path: &str,
+    read_only: bool,
+    name: &str,
+) -> HRESULT {
+    let Some(mount_folder) = api.MountFolder else {
+        return E_NOTIMPL;
+    };
+    let windows_path = to_wide_string(windows_path);
+    let linux_path = to_wide_string(linux_path);
+    let name = to_wide_string(name);
+    mount_folder(
+        session,
+        PCWSTR(windows_path.as_ptr()),
+        PCWSTR(linux_path.as_ptr()),
+        read_only.into(),
+        PCWSTR(name.as_ptr()),
+    )
+}
+
+/// Calls `WSLPluginAPI_PluginError` with a Rust string.
+///
+/// # Safety
+///
+/// `api` must be the table received at the plugin entry point, and the call must happen
+/// synchronously in `OnVMStarted` or `OnDistributionStarted`.
+pub unsafe fn plugin_error(api: &WSLPluginAPIV1, message: &str) -> HRESULT {
+    let Some(plugin_error) = api.PluginError else {
+        return E_NOTIMPL;
+    };
+    let message = to_wide_string(message);
+    plugin_error(PCWSTR(message.as_ptr()))
+}
+
+struct ArgumentVector {
+    path: CString,
+    // Keeps the strings alive while `pointers` refers to them.
+    _arguments: Vec<CString>,
+    pointers: Vec<PCSTR>,
+}
+
+impl ArgumentVector {
+    fn new(path: &str, arguments: &[&str]) -> Result<Self> {
+        let to_c_string = |s: &str| CString::new(s).map_err(|_| Error::from_hresult(E_INVALIDARG));
+        let path = to_c_string(path)?;
+        let arguments = arguments
+            .iter()
+            .map(|argument| to_c_string(argument))
+            .collect::<Result<Vec<_>>>()?;
+        let pointers = arguments
+            .iter()
+            .map(|argument| PCSTR(argument.as_ptr().cast()))
+            .chain(std::iter::once(PCSTR::null()))
+            .collect();
+        Ok(Self {
+            path,
+            _arguments: arguments,
+            pointers,
+        })
+    }
+
+    fn path(&self) -> PCSTR {
+        PCSTR(self.path.as_ptr().cast())
+    }
+}
+
+/// Calls `WSLPluginAPI_ExecuteBinary` to start `path` in the root namespace of the VM.
+///
+/// `arguments` is the full argument vector, `argv[0]` included. On success the returned stream
+/// is connected to the standard input and output of the process; its standard error goes to
+/// `dmesg`.
+///
+/// # Safety
+///
+/// `api` must be the table received at the plugin entry point.
+pub unsafe fn execute_binary(
+    api: &WSLPluginAPIV1,
+    session: WSLSessionId,
+    path: &str,
+    arguments: &[&str],
+) -> Result<ProcessStream> {
+    let execute_binary = api.ExecuteBinary.ok_or(Error::from_hresult(E_NOTIMPL))?;
+    let mut arguments = ArgumentVector::new(path, arguments)?;
+    let mut socket = SOCKET::default();
+    execute_binary(
+        session,
+        arguments.path(),
+        arguments.pointers.as_mut_ptr(),
+        &mut socket,
+    )
+    .ok()?;
+    Ok(process_stream_from_socket(socket))
+}
+
+/// Calls `WSLPluginAPI_ExecuteBinaryInDistribution` to start `path` in a user distribution.
+///
+/// See [`execute_binary`] for the meaning of `arguments` and of the returned stream.
+///
+/// # Safety
+///
+/// `api` must be the table received at the plugin entry point, and WSL must be at least 2.1.2.
+#[cfg(wsl_plugin_api_ge_2_1_2)]
+pub unsafe fn execute_binary_in_distribution(
+    api: &WSLPluginAPIV1,
+    session: WSLSessionId,
+    distribution: &GUID,
+    path: &str,
+    arguments: &[&str],
+) -> Result<ProcessStream> {
+    let execute_binary = api
+        .ExecuteBinaryInDistribution
+        .ok_or(Error::from_hresult(E_NOTIMPL))?;
+    let mut arguments = ArgumentVector::new(path, arguments)?;
+    let mut socket = SOCKET::default();
+    execute_binary(
+        session,
+        distribution,
+        arguments.path(),
+        arguments.pointers.as_mut_ptr(),
+        &mut socket,
+    )
+    .ok()?;
+    Ok(process_stream_from_socket(socket))
+}
@@ -0,0 +1,463 @@
+use crate::strings::read_wide_string;
+use crate::windows::core::{GUID, PCWSTR};
+use crate::WSLDistributionInformation;
+#[cfg(wsl_plugin_api_ge_2_1_2)]
+use crate::WslOfflineDistributionInformation;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+/// Reads the `Flavor` and `Version` fields, which only exist in the 2.4.4 headers onwards.
+#[cfg(wsl_plugin_api_ge_2_4_4)]
+macro_rules! flavor_and_version {
+    ($info:expr) => {
+        ($info.Flavor, $info.Version)
+    };
+}
+
+#[cfg(not(wsl_plugin_api_ge_2_4_4))]
+macro_rules! flavor_and_version {
+    ($info:expr) => {{
+        let _ = $info;
+        (PCWSTR::null(), PCWSTR::null())
+    }};
+}
+
+/// Known distribution families as reported by the `Flavor` field.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DistributionFlavor {
+    Ubuntu,
+    Debian,
+    Fedora,
+    Arch,
+    OpenSuse,
+    Alpine,
+    /// Any flavor not covered by the other variants, stored in lowercase.
+    Other(String),
+}
+
+impl DistributionFlavor {
+    /// Returns the canonical lowercase name of the flavor.
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::Ubuntu => "ubuntu",
+            Self::Debian => "debian",
+            Self::Fedora => "fedora",
+            Self::Arch => "arch",
+            Self::OpenSuse => "opensuse",
+            Self::Alpine => "alpine",
+            Self::Other(other) => other,
+        }
+    }
+}
+
+impl FromStr for DistributionFlavor {
+    type Err = std::convert::Infallible;
+
+    /// Parses a flavor case-insensitively. Unknown values are kept as [`DistributionFlavor::Other`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let flavor = s.trim().to_ascii_lowercase();
+        Ok(match flavor.as_str() {
+            "ubuntu" => Self::Ubuntu,
+            "debian" => Self::Debian,
+            "fedora" => Self::Fedora,
+            "arch" | "archlinux" => Self::Arch,
+            "alpine" => Self::Alpine,
+            f if f.starts_with("opensuse") => Self::OpenSuse,
+            _ => Self::Other(flavor),
+        })
+    }
+}
+
+impl fmt::Display for DistributionFlavor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A distribution version such as `22.04`, `3.19.1` or `rolling`.
+///
+/// Versions are ordered by their dotted numeric components, missing components counting as zero
+/// (`22.04` == `22.4.0`). The text that follows the numeric part, if any, only breaks ties.
+#[derive(Debug, Clone)]
+pub struct DistributionVersion {
+    raw: String,
+    components: Vec<u32>,
+    suffix: String,
+}
+
+impl DistributionVersion {
+    /// Builds a version from its numeric components.
+    pub fn new<I: IntoIterator<Item = u32>>(components: I) -> Self {
+        let components: Vec<u32> = components.into_iter().collect();
+        let raw = components
+            .iter()
+            .map(u32::to_string)
+            .collect::<Vec<_>>()
+            .join(".");
+        Self {
+            raw,
+            components,
+            suffix: String::new(),
+        }
+    }
+
+    /// The version exactly as reported by WSL.
+    pub fn as_str(&self) -> &str {
+        &self.raw
+    }
+
+    /// The numeric components of the version, empty for versions like `rolling`.
+    pub fn components(&self) -> &[u32] {
+        &self.components
+    }
+
+    /// The major component, if the version starts with a number.
+    pub fn major(&self) -> Option<u32> {
+        self.components.first().copied()
+    }
+
+    /// The minor component, if any.
+    pub fn minor(&self) -> Option<u32> {
+        self.components.get(1).copied()
+    }
+
+    /// The non-numeric remainder of the version (for example `LTS` in `22.04 LTS`).
+    pub fn suffix(&self) -> &str {
+        &self.suffix
+    }
+
+    fn significant_components(&self) -> &[u32] {
+        let len = self
+            .components
+            .iter()
+            .rposition(|c| *c != 0)
+            .map_or(0, |i| i + 1);
+        &self.components[..len]
+    }
+}
+
+impl FromStr for DistributionVersion {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let raw = s.trim();
+        let mut components = Vec::new();
+        let mut rest = raw;
+        loop {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            if digits == 0 {
+                break;
+            }
+            match rest[..digits].parse() {
+                Ok(component) => components.push(component),
+                Err(_) => break,
+            }
+            rest = &rest[digits..];
+            match rest.strip_prefix('.') {
+                Some(next) if next.starts_with(|c: char| c.is_ascii_digit()) => rest = next,
+                _ => break,
+            }
+        }
+        let suffix = rest
+            .trim_start_matches(['.', '-', '_', '~', '+', ' '])
+            .to_owned();
+        Ok(Self {
+            raw: raw.to_owned(),
+            components,
+            suffix,
+        })
+    }
+}
+
+impl fmt::Display for DistributionVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.raw)
+    }
+}
+
+impl PartialEq for DistributionVersion {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for DistributionVersion {}
+
+impl PartialOrd for DistributionVersion {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for DistributionVersion {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let len = self.components.len().max(other.components.len());
+        (0..len)
+            .map(|i| {
+                let lhs = self.components.get(i).copied().unwrap_or(0);
+                let rhs = other.components.get(i).copied().unwrap_or(0);
+                lhs.cmp(&rhs)
+            })
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| {
+                self.suffix
+                    .to_ascii_lowercase()
+                    .cmp(&other.suffix.to_ascii_lowercase())
+            })
+    }
+}
+
+impl Hash for DistributionVersion {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.significant_components().hash(state);
+        self.suffix.to_ascii_lowercase().hash(state);
+    }
+}
+
+/// Error returned when a package family name is not of the form `<name>_<publisher id>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsePackageFamilyNameError(String);
+
+impl fmt::Display for ParsePackageFamilyNameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid package family name: {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParsePackageFamilyNameError {}
+
+/// A package family name such as `CanonicalGroupLimited.Ubuntu22.04LTS_79rhkp1fndgsc`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PackageFamilyName {
+    name: String,
+    publisher_id: String,
+}
+
+impl PackageFamilyName {
+    /// The package name part (`CanonicalGroupLimited.Ubuntu22.04LTS`).
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The publisher id part (`79rhkp1fndgsc`).
+    pub fn publisher_id(&self) -> &str {
+        &self.publisher_id
+    }
+}
+
+impl FromStr for PackageFamilyName {
+    type Err = ParsePackageFamilyNameError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().rsplit_once('_') {
+            Some((name, publisher_id))
+                if !name.is_empty()
+                    && !publisher_id.is_empty()
+                    && publisher_id.chars().all(|c| c.is_ascii_alphanumeric()) =>
+            {
+                Ok(Self {
+                    name: name.to_owned(),
+                    publisher_id: publisher_id.to_owned(),
+                })
+            }
+            _ => Err(ParsePackageFamilyNameError(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for PackageFamilyName {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.name, self.publisher_id)
+    }
+}
+
+/// Typed view of the `Flavor`, `Version` and `PackageFamilyName` fields of a distribution.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct DistributionMetadata {
+    pub flavor: Option<DistributionFlavor>,
+    pub version: Option<DistributionVersion>,
+    /// `None` if the distribution was not installed from a package or if the name is malformed.
+    pub package_family_name: Option<PackageFamilyName>,
+}
+
+impl DistributionMetadata {
+    /// Builds the metadata from already decoded strings. Empty strings are treated as absent.
+    pub fn from_parts(
+        flavor: Option<&str>,
+        version: Option<&str>,
+        package_family_name: Option<&str>,
+    ) -> Self {
+        fn non_empty(s: Option<&str>) -> Option<&str> {
+            s.map(str::trim).filter(|s| !s.is_empty())
+        }
+        Self {
+            flavor: non_empty(flavor).and_then(|s| s.parse().ok()),
+            version: non_empty(version).and_then(|s| s.parse().ok()),
+            package_family_name: non_empty(package_family_name).and_then(|s| s.parse().ok()),
+        }
+    }
+
+    /// Decodes the metadata of a running distribution.
+    ///
+    /// # Safety
+    ///
+    /// The string fields of `info` must be null or valid null-terminated UTF-16 strings, and the
+    /// structure must come from a WSL release that populates `Flavor` and `Version`.
+    pub unsafe fn from_distribution_information(info: &WSLDistributionInformation) -> Self {
+        let (flavor, version) = flavor_and_version!(info);
+        Self::from_wide(flavor, version, info.PackageFamilyName)
+    }
+
+    /// Decodes the metadata of a registered (not necessarily running) distribution.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`DistributionMetadata::from_distribution_information`].
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    pub unsafe fn from_offline_distribution_information(
+        info: &WslOfflineDistributionInformation,
+    ) -> Self {
+        let (flavor, version) = flavor_and_version!(info);
+        Self::from_wide(flavor, version, info.PackageFamilyName)
+    }
+
+    unsafe fn from_wide(flavor: PCWSTR, version: PCWSTR, package_family_name: PCWSTR) -> Self {
+        Self::from_parts(
+            read_wide_string(flavor).as_deref(),
+            read_wide_string(version).as_deref(),
+            read_wide_string(package_family_name).as_deref(),
+        )
+    }
+
+    /// Returns `true` if the distribution was installed from a package (Microsoft Store, winget, ...).
+    pub fn is_packaged(&self) -> bool {
+        self.package_family_name.is_some()
+    }
+}
+
+/// Owned snapshot of the information WSL hands out about a distribution.
+///
+/// Unlike [`WSLDistributionInformation`], it remains valid after the hook returns.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DistributionInfo {
+    pub id: GUID,
+    pub name: String,
+    pub metadata: DistributionMetadata,
+}
+
+impl DistributionInfo {
+    /// Copies the information of a running distribution.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`DistributionMetadata::from_distribution_information`].
+    pub unsafe fn from_distribution_information(info: &WSLDistributionInformation) -> Self {
+        Self {
+            id: info.Id,
+            name: read_wide_string(info.Name).unwrap_or_default(),
+            metadata: DistributionMetadata::from_distribution_information(info),
+        }
+    }
+
+    /// Copies the information of a registered distribution.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`DistributionMetadata::from_distribution_information`].
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    pub unsafe fn from_offline_distribution_information(
+        info: &WslOfflineDistributionInformation,
+    ) -> Self {
+        Self {
+            id: info.Id,
+            name: read_wide_string(info.Name).unwrap_or_default(),
+            metadata: DistributionMetadata::from_offline_distribution_information(info),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    use crate::strings::to_wide_string;
+
+    #[test]
+    fn test_flavor_parsing() {
+        assert_eq!("Ubuntu".parse(), Ok(DistributionFlavor::Ubuntu));
+        assert_eq!("archlinux".parse(), Ok(DistributionFlavor::Arch));
+        assert_eq!(
+            "opensuse-tumbleweed".parse(),
+            Ok(DistributionFlavor::OpenSuse)
+        );
+        assert_eq!(
+            "NixOS".parse(),
+            Ok(DistributionFlavor::Other("nixos".into()))
+        );
+    }
+
+    #[test]
+    fn test_version_ordering() {
+        let parse = |s: &str| s.parse::<DistributionVersion>().unwrap();
+        assert!(parse("22.04") < parse("24.04"));
+        assert!(parse("3.9") < parse("3.19.1"));
+        assert_eq!(parse("22.04"), parse("22.4.0"));
+        assert_eq!(parse("22.04"), DistributionVersion::new([22, 4]));
+        assert_eq!(parse("22.04 LTS").suffix(), "LTS");
+        assert_eq!(parse("rolling").major(), None);
+        assert_eq!(parse("22.04").to_string(), "22.04");
+    }
+
+    #[test]
+    fn test_package_family_name() {
+        let pfn: PackageFamilyName = "CanonicalGroupLimited.Ubuntu22.04LTS_79rhkp1fndgsc"
+            .parse()
+            .unwrap();
+        assert_eq!(pfn.name(), "CanonicalGroupLimited.Ubuntu22.04LTS");
+        assert_eq!(pfn.publisher_id(), "79rhkp1fndgsc");
+        assert!("NoPublisher".parse::<PackageFamilyName>().is_err());
+        assert!("_79rhkp1fndgsc".parse::<PackageFamilyName>().is_err());
+    }
+
+    #[cfg(wsl_plugin_api_ge_2_4_4)]
+    #[test]
+    fn test_metadata_from_distribution_information() {
+        let name = to_wide_string("Ubuntu-22.04");
+        let pfn = to_wide_string("CanonicalGroupLimited.Ubuntu22.04LTS_79rhkp1fndgsc");
+        let flavor = to_wide_string("ubuntu");
+        let version = to_wide_string("22.04");
+        let info = WSLDistributionInformation {
+            Id: GUID::zeroed(),
+            Name: PCWSTR(name.as_ptr()),
+            PidNamespace: 0,
+            PackageFamilyName: PCWSTR(pfn.as_ptr()),
+            InitPid: 1,
+            Flavor: PCWSTR(flavor.as_ptr()),
+            Version: PCWSTR(version.as_ptr()),
+        };
+        let metadata = unsafe { DistributionMetadata::from_distribution_information(&info) };
+        assert_eq!(metadata.flavor, Some(DistributionFlavor::Ubuntu));
+        assert_eq!(metadata.version, Some(DistributionVersion::new([22, 4])));
+        assert!(metadata.is_packaged());
+    }
+
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    #[test]
+    fn test_metadata_with_null_fields() {
+        let name = to_wide_string("custom");
+        let info = WslOfflineDistributionInformation {
+            Id: GUID::zeroed(),
+            Name: PCWSTR(name.as_ptr()),
+            PackageFamilyName: PCWSTR::null(),
+            #[cfg(wsl_plugin_api_ge_2_4_4)]
+            Flavor: PCWSTR::null(),
+            #[cfg(wsl_plugin_api_ge_2_4_4)]
+            Version: PCWSTR::null(),
+        };
+        let metadata =
+            unsafe { DistributionMetadata::from_offline_distribution_information(&info) };
+        assert_eq!(metadata, DistributionMetadata::default());
+    }
+}
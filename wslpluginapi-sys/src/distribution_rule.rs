@@ -0,0 +1,817 @@
+use crate::windows::core::GUID;
+#[cfg(wsl_plugin_api_ge_2_1_2)]
+use crate::WslOfflineDistributionInformation;
+use crate::{
+    DistributionFlavor, DistributionInfo, DistributionVersion, WSLDistributionInformation,
+};
+use std::fmt;
+use std::str::FromStr;
+
+/// A wildcard pattern where `*` matches any sequence and `?` any character.
+///
+/// Matching ignores case unless [`GlobPattern::matches_case_sensitive`] is used. Paths are
+/// matched with [`GlobPattern::matches_path`], where wildcards stay within a directory.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct GlobPattern(String);
+
+impl GlobPattern {
+    pub fn new<S: Into<String>>(pattern: S) -> Self {
+        Self(pattern.into())
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Returns `true` if `value` matches the whole pattern, ignoring case.
+    pub fn matches(&self, value: &str) -> bool {
+        let pattern: Vec<char> = self.0.chars().flat_map(char::to_lowercase).collect();
+        let value: Vec<char> = value.chars().flat_map(char::to_lowercase).collect();
+        glob_match(&pattern, &value, None)
+    }
+
+    /// Returns `true` if `value` matches the whole pattern, case included.
+    pub fn matches_case_sensitive(&self, value: &str) -> bool {
+        let pattern: Vec<char> = self.0.chars().collect();
+        let value: Vec<char> = value.chars().collect();
+        glob_match(&pattern, &value, None)
+    }
+
+    /// Returns `true` if the Linux path `value` matches the whole pattern, case included.
+    ///
+    /// `*` and `?` do not match `/`, so `/opt/bin/*` does not match `/opt/bin/sub/tool`; `**`
+    /// matches any sequence, `/` included.
+    pub fn matches_path(&self, value: &str) -> bool {
+        let pattern: Vec<char> = self.0.chars().collect();
+        let value: Vec<char> = value.chars().collect();
+        glob_match(&pattern, &value, Some('/'))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum GlobToken {
+    Char(char),
+    /// `?`
+    One,
+    /// `*` within a path component.
+    Component,
+    /// `*`, or `**` when matching paths.
+    Any,
+}
+
+/// Matches `value` against `pattern`; with a `separator`, `*` and `?` do not match it.
+fn glob_match(pattern: &[char], value: &[char], separator: Option<char>) -> bool {
+    let mut tokens = Vec::with_capacity(pattern.len());
+    let mut chars = pattern.iter().copied().peekable();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            '?' => GlobToken::One,
+            '*' if separator.is_none() => GlobToken::Any,
+            '*' if chars.next_if_eq(&'*').is_some() => GlobToken::Any,
+            '*' => GlobToken::Component,
+            c => GlobToken::Char(c),
+        });
+    }
+    let free = |c: char| Some(c) != separator;
+    // `next[j]` tells whether the tokens after the current one match `value[j..]`.
+    let mut next = vec![false; value.len() + 1];
+    next[value.len()] = true;
+    for token in tokens.iter().rev() {
+        let mut current = vec![false; value.len() + 1];
+        for j in (0..=value.len()).rev() {
+            let c = value.get(j).copied();
+            current[j] = match *token {
+                GlobToken::Char(expected) => c == Some(expected) && next[j + 1],
+                GlobToken::One => c.is_some_and(free) && next[j + 1],
+                GlobToken::Component => next[j] || (c.is_some_and(free) && current[j + 1]),
+                GlobToken::Any => next[j] || (c.is_some() && current[j + 1]),
+            };
+        }
+        next = current;
+    }
+    next[0]
+}
+
+impl From<&str> for GlobPattern {
+    fn from(pattern: &str) -> Self {
+        Self::new(pattern)
+    }
+}
+
+impl From<String> for GlobPattern {
+    fn from(pattern: String) -> Self {
+        Self(pattern)
+    }
+}
+
+impl fmt::Display for GlobPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+/// Comparison operator used by version predicates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum VersionComparison {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+}
+
+impl VersionComparison {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Equal => "=",
+            Self::NotEqual => "!=",
+            Self::Less => "<",
+            Self::LessOrEqual => "<=",
+            Self::Greater => ">",
+            Self::GreaterOrEqual => ">=",
+        }
+    }
+
+    fn evaluate(self, lhs: &DistributionVersion, rhs: &DistributionVersion) -> bool {
+        match self {
+            Self::Equal => lhs == rhs,
+            Self::NotEqual => lhs != rhs,
+            Self::Less => lhs < rhs,
+            Self::LessOrEqual => lhs <= rhs,
+            Self::Greater => lhs > rhs,
+            Self::GreaterOrEqual => lhs >= rhs,
+        }
+    }
+}
+
+/// A rule deciding which distributions a plugin acts on.
+///
+/// Rules can be assembled from code or parsed from their textual form, for example
+/// `flavor = ubuntu && version >= 22.04 && packaged` or `name != "docker-desktop*"`.
+///
+/// The textual grammar is:
+///
+/// ```text
+/// rule      := and ( "||" and )*
+/// and       := unary ( "&&" unary )*
+/// unary     := "!" unary | "(" rule ")" | predicate
+/// predicate := "all" | "packaged"
+///            | ("name" | "package") ("=" | "!=") <glob>
+///            | "flavor" ("=" | "!=") <flavor>
+///            | "id" ("=" | "!=") <guid>
+///            | "version" ("=" | "!=" | "<" | "<=" | ">" | ">=") <version>
+/// ```
+///
+/// Values are bare words or double-quoted strings. `package` matches the full package family
+/// name. A distribution that does not report a version never satisfies a version predicate.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum DistributionRule {
+    /// Matches every distribution.
+    All,
+    /// Matches distributions installed from a package (Microsoft Store, winget, ...).
+    Packaged,
+    Name(GlobPattern),
+    Flavor(DistributionFlavor),
+    Version(VersionComparison, DistributionVersion),
+    PackageFamilyName(GlobPattern),
+    Id(GUID),
+    Not(Box<DistributionRule>),
+    And(Vec<DistributionRule>),
+    Or(Vec<DistributionRule>),
+}
+
+impl DistributionRule {
+    pub fn name<P: Into<GlobPattern>>(pattern: P) -> Self {
+        Self::Name(pattern.into())
+    }
+
+    pub fn flavor(flavor: DistributionFlavor) -> Self {
+        Self::Flavor(flavor)
+    }
+
+    pub fn version(comparison: VersionComparison, version: DistributionVersion) -> Self {
+        Self::Version(comparison, version)
+    }
+
+    pub fn package_family_name<P: Into<GlobPattern>>(pattern: P) -> Self {
+        Self::PackageFamilyName(pattern.into())
+    }
+
+    pub fn id(id: GUID) -> Self {
+        Self::Id(id)
+    }
+
+    /// Combines two rules so that both must match.
+    pub fn and(self, other: Self) -> Self {
+        match self {
+            Self::And(mut rules) => {
+                rules.push(other);
+                Self::And(rules)
+            }
+            rule => Self::And(vec![rule, other]),
+        }
+    }
+
+    /// Combines two rules so that at least one must match.
+    pub fn or(self, other: Self) -> Self {
+        match self {
+            Self::Or(mut rules) => {
+                rules.push(other);
+                Self::Or(rules)
+            }
+            rule => Self::Or(vec![rule, other]),
+        }
+    }
+
+    /// Evaluates the rule against a distribution snapshot.
+    pub fn matches(&self, distribution: &DistributionInfo) -> bool {
+        let metadata = &distribution.metadata;
+        match self {
+            Self::All => true,
+            Self::Packaged => metadata.is_packaged(),
+            Self::Name(pattern) => pattern.matches(&distribution.name),
+            Self::Flavor(flavor) => metadata.flavor.as_ref() == Some(flavor),
+            Self::Version(comparison, version) => metadata
+                .version
+                .as_ref()
+                .is_some_and(|v| comparison.evaluate(v, version)),
+            Self::PackageFamilyName(pattern) => metadata
+                .package_family_name
+                .as_ref()
+                .is_some_and(|pfn| pattern.matches(&pfn.to_string())),
+            Self::Id(id) => distribution.id == *id,
+            Self::Not(rule) => !rule.matches(distribution),
+            Self::And(rules) => rules.iter().all(|rule| rule.matches(distribution)),
+            Self::Or(rules) => rules.iter().any(|rule| rule.matches(distribution)),
+        }
+    }
+
+    /// Evaluates the rule against the distribution passed to a hook.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`DistributionInfo::from_distribution_information`].
+    pub unsafe fn matches_distribution(&self, info: &WSLDistributionInformation) -> bool {
+        self.matches(&DistributionInfo::from_distribution_information(info))
+    }
+
+    /// Evaluates the rule against the distribution passed to a registration hook.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`DistributionInfo::from_offline_distribution_information`].
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    pub unsafe fn matches_offline_distribution(
+        &self,
+        info: &WslOfflineDistributionInformation,
+    ) -> bool {
+        self.matches(&DistributionInfo::from_offline_distribution_information(
+            info,
+        ))
+    }
+
+    fn is_predicate(&self) -> bool {
+        !matches!(self, Self::Not(_) | Self::And(_) | Self::Or(_))
+    }
+}
+
+impl std::ops::Not for DistributionRule {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self {
+            Self::Not(rule) => *rule,
+            rule => Self::Not(Box::new(rule)),
+        }
+    }
+}
+
+fn write_value(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    if !value.is_empty() && value.chars().all(is_word_char) {
+        f.write_str(value)
+    } else {
+        write!(
+            f,
+            "\"{}\"",
+            value.replace('\\', "\\\\").replace('"', "\\\"")
+        )
+    }
+}
+
+fn write_predicate(
+    f: &mut fmt::Formatter<'_>,
+    rule: &DistributionRule,
+    negated: bool,
+) -> fmt::Result {
+    let op = if negated { "!=" } else { "=" };
+    match rule {
+        DistributionRule::Name(pattern) => {
+            write!(f, "name {op} ")?;
+            write_value(f, pattern.as_str())
+        }
+        DistributionRule::Flavor(flavor) => {
+            write!(f, "flavor {op} ")?;
+            write_value(f, flavor.as_str())
+        }
+        DistributionRule::PackageFamilyName(pattern) => {
+            write!(f, "package {op} ")?;
+            write_value(f, pattern.as_str())
+        }
+        DistributionRule::Id(id) => write!(f, "id {op} {id:?}"),
+        _ => unreachable!("not a negatable predicate"),
+    }
+}
+
+impl fmt::Display for DistributionRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let write_group = |f: &mut fmt::Formatter<'_>, rules: &[Self], separator: &str| {
+            for (i, rule) in rules.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(separator)?;
+                }
+                if matches!(rule, Self::And(_) | Self::Or(_)) {
+                    write!(f, "({rule})")?;
+                } else {
+                    write!(f, "{rule}")?;
+                }
+            }
+            Ok(())
+        };
+        match self {
+            Self::All => f.write_str("all"),
+            Self::Packaged => f.write_str("packaged"),
+            Self::Version(comparison, version) => {
+                write!(f, "version {} ", comparison.as_str())?;
+                write_value(f, version.as_str())
+            }
+            Self::Not(rule) => match rule.as_ref() {
+                Self::Name(_) | Self::Flavor(_) | Self::PackageFamilyName(_) | Self::Id(_) => {
+                    write_predicate(f, rule, true)
+                }
+                rule if rule.is_predicate() => write!(f, "!{rule}"),
+                rule => write!(f, "!({rule})"),
+            },
+            // Empty groups have no syntax, they print as the rule they are equivalent to.
+            Self::And(rules) if rules.is_empty() => f.write_str("all"),
+            Self::Or(rules) if rules.is_empty() => f.write_str("!all"),
+            Self::And(rules) => write_group(f, rules, " && "),
+            Self::Or(rules) => write_group(f, rules, " || "),
+            predicate => write_predicate(f, predicate, false),
+        }
+    }
+}
+
+/// Error returned when the textual form of a [`DistributionRule`] is invalid.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseDistributionRuleError {
+    offset: usize,
+    message: String,
+}
+
+impl ParseDistributionRuleError {
+    fn new<S: Into<String>>(offset: usize, message: S) -> Self {
+        Self {
+            offset,
+            message: message.into(),
+        }
+    }
+
+    /// Byte offset in the input where the error was detected.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl fmt::Display for ParseDistributionRuleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl std::error::Error for ParseDistributionRuleError {}
+
+fn is_word_char(c: char) -> bool {
+    !c.is_whitespace() && !matches!(c, '(' | ')' | '!' | '&' | '|' | '=' | '<' | '>' | '"')
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    Quoted(String),
+    Operator(&'static str),
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    position: usize,
+    end: usize,
+}
+
+impl Parser {
+    fn tokenize(input: &str) -> Result<Self, ParseDistributionRuleError> {
+        const OPERATORS: [&str; 12] = [
+            "&&", "||", "!=", "<=", ">=", "==", "=", "<", ">", "!", "(", ")",
+        ];
+        let mut tokens = Vec::new();
+        let mut chars = input.char_indices().peekable();
+        while let Some(&(offset, c)) = chars.peek() {
+            let rest = &input[offset..];
+            if c.is_whitespace() {
+                chars.next();
+            } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+                // `==` is accepted as an alias of `=`.
+                let token = if *op == "==" { "=" } else { op };
+                tokens.push((offset, Token::Operator(token)));
+                for _ in 0..op.len() {
+                    chars.next();
+                }
+            } else if c == '"' {
+                chars.next();
+                let mut value = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, escaped)) => value.push(escaped),
+                            None => {
+                                return Err(ParseDistributionRuleError::new(
+                                    offset,
+                                    "unterminated string",
+                                ))
+                            }
+                        },
+                        Some((_, c)) => value.push(c),
+                        None => {
+                            return Err(ParseDistributionRuleError::new(
+                                offset,
+                                "unterminated string",
+                            ))
+                        }
+                    }
+                }
+                tokens.push((offset, Token::Quoted(value)));
+            } else if is_word_char(c) {
+                let mut word = String::new();
+                while let Some(&(_, c)) = chars.peek() {
+                    if !is_word_char(c) {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                tokens.push((offset, Token::Word(word)));
+            } else {
+                return Err(ParseDistributionRuleError::new(
+                    offset,
+                    format!("unexpected character {c:?}"),
+                ));
+            }
+        }
+        Ok(Self {
+            tokens,
+            position: 0,
+            end: input.len(),
+        })
+    }
+
+    fn offset(&self) -> usize {
+        self.tokens
+            .get(self.position)
+            .map_or(self.end, |(offset, _)| *offset)
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position).map(|(_, token)| token)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.peek().cloned();
+        self.position += 1;
+        token
+    }
+
+    fn eat(&mut self, operator: &'static str) -> bool {
+        if self.peek() == Some(&Token::Operator(operator)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn error<T, S: Into<String>>(&self, message: S) -> Result<T, ParseDistributionRuleError> {
+        Err(ParseDistributionRuleError::new(self.offset(), message))
+    }
+
+    fn parse_or(&mut self) -> Result<DistributionRule, ParseDistributionRuleError> {
+        let mut rules = vec![self.parse_and()?];
+        while self.eat("||") {
+            rules.push(self.parse_and()?);
+        }
+        Ok(if rules.len() == 1 {
+            rules.remove(0)
+        } else {
+            DistributionRule::Or(rules)
+        })
+    }
+
+    fn parse_and(&mut self) -> Result<DistributionRule, ParseDistributionRuleError> {
+        let mut rules = vec![self.parse_unary()?];
+        while self.eat("&&") {
+            rules.push(self.parse_unary()?);
+        }
+        Ok(if rules.len() == 1 {
+            rules.remove(0)
+        } else {
+            DistributionRule::And(rules)
+        })
+    }
+
+    fn parse_unary(&mut self) -> Result<DistributionRule, ParseDistributionRuleError> {
+        if self.eat("!") {
+            Ok(!self.parse_unary()?)
+        } else if self.eat("(") {
+            let rule = self.parse_or()?;
+            if !self.eat(")") {
+                return self.error("expected `)`");
+            }
+            Ok(rule)
+        } else {
+            self.parse_predicate()
+        }
+    }
+
+    fn parse_predicate(&mut self) -> Result<DistributionRule, ParseDistributionRuleError> {
+        let key_offset = self.offset();
+        let key = match self.next() {
+            Some(Token::Word(word)) => word.to_ascii_lowercase(),
+            _ => {
+                return Err(ParseDistributionRuleError::new(
+                    key_offset,
+                    "expected a predicate",
+                ))
+            }
+        };
+        match key.as_str() {
+            "all" => return Ok(DistributionRule::All),
+            "packaged" => return Ok(DistributionRule::Packaged),
+            _ => {}
+        }
+        let operator = match self.next() {
+            Some(Token::Operator(op)) if matches!(op, "=" | "!=" | "<" | "<=" | ">" | ">=") => op,
+            _ => {
+                self.position -= 1;
+                return self.error(format!("expected a comparison after `{key}`"));
+            }
+        };
+        let value_offset = self.offset();
+        let value = match self.next() {
+            Some(Token::Word(value) | Token::Quoted(value)) => value,
+            _ => {
+                return Err(ParseDistributionRuleError::new(
+                    value_offset,
+                    "expected a value",
+                ))
+            }
+        };
+        let predicate = match key.as_str() {
+            "version" => {
+                let comparison = match operator {
+                    "=" => VersionComparison::Equal,
+                    "!=" => VersionComparison::NotEqual,
+                    "<" => VersionComparison::Less,
+                    "<=" => VersionComparison::LessOrEqual,
+                    ">" => VersionComparison::Greater,
+                    _ => VersionComparison::GreaterOrEqual,
+                };
+                let Ok(version) = value.parse();
+                return Ok(DistributionRule::Version(comparison, version));
+            }
+            "name" => DistributionRule::Name(value.into()),
+            "package" => DistributionRule::PackageFamilyName(value.into()),
+            "flavor" => {
+                let Ok(flavor) = value.parse();
+                DistributionRule::Flavor(flavor)
+            }
+            "id" => {
+                let guid = GUID::try_from(value.trim_start_matches('{').trim_end_matches('}'))
+                    .map_err(|_| {
+                        ParseDistributionRuleError::new(
+                            value_offset,
+                            format!("invalid GUID {value:?}"),
+                        )
+                    })?;
+                DistributionRule::Id(guid)
+            }
+            _ => {
+                return Err(ParseDistributionRuleError::new(
+                    key_offset,
+                    format!("unknown predicate `{key}`"),
+                ))
+            }
+        };
+        match operator {
+            "=" => Ok(predicate),
+            "!=" => Ok(!predicate),
+            _ => Err(ParseDistributionRuleError::new(
+                key_offset,
+                format!("`{key}` only supports `=` and `!=`"),
+            )),
+        }
+    }
+}
+
+impl FromStr for DistributionRule {
+    type Err = ParseDistributionRuleError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser::tokenize(s)?;
+        let rule = parser.parse_or()?;
+        if parser.peek().is_some() {
+            return parser.error("unexpected trailing input");
+        }
+        Ok(rule)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for GlobPattern {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for GlobPattern {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for DistributionRule {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for DistributionRule {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let text = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        text.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DistributionMetadata;
+
+    fn distribution(
+        name: &str,
+        flavor: &str,
+        version: &str,
+        pfn: Option<&str>,
+    ) -> DistributionInfo {
+        DistributionInfo {
+            id: GUID::from_u128(0x12345678_1234_1234_1234_123456789abc),
+            name: name.into(),
+            metadata: DistributionMetadata::from_parts(Some(flavor), Some(version), pfn),
+        }
+    }
+
+    #[test]
+    fn test_glob_pattern() {
+        let pattern = GlobPattern::new("docker-desktop*");
+        assert!(pattern.matches("docker-desktop"));
+        assert!(pattern.matches("Docker-Desktop-Data"));
+        assert!(!pattern.matches("ubuntu"));
+        assert!(GlobPattern::new("u?untu*").matches("Ubuntu-22.04"));
+        assert!(GlobPattern::new("*a*b").matches("xaxxab"));
+        assert!(!GlobPattern::new("*a*b").matches("xaxxa"));
+    }
+
+    #[test]
+    fn test_glob_path() {
+        let pattern = GlobPattern::new("/opt/plugin/bin/*");
+        assert!(pattern.matches_path("/opt/plugin/bin/agent"));
+        assert!(!pattern.matches_path("/opt/plugin/bin/sub/tool"));
+        assert!(!pattern.matches_path("/OPT/plugin/bin/agent"));
+        assert!(pattern.matches_case_sensitive("/opt/plugin/bin/sub/tool"));
+        assert!(!GlobPattern::new("/opt/?").matches_path("/opt//"));
+        let pattern = GlobPattern::new("/opt/plugin/**");
+        assert!(pattern.matches_path("/opt/plugin/bin/sub/tool"));
+        assert!(!pattern.matches_path("/opt/plugin"));
+        let pattern = GlobPattern::new("/srv/**/*.conf");
+        assert!(pattern.matches_path("/srv/a/b/c.conf"));
+        assert!(!pattern.matches_path("/srv/c.conf"));
+    }
+
+    #[test]
+    fn test_store_ubuntu_rule() {
+        let rule: DistributionRule = "flavor = ubuntu && version >= 22.04 && packaged"
+            .parse()
+            .unwrap();
+        let store = Some("CanonicalGroupLimited.Ubuntu22.04LTS_79rhkp1fndgsc");
+        assert!(rule.matches(&distribution("Ubuntu-22.04", "ubuntu", "22.04", store)));
+        assert!(rule.matches(&distribution("Ubuntu", "ubuntu", "24.04", store)));
+        assert!(!rule.matches(&distribution("Ubuntu-20.04", "ubuntu", "20.04", store)));
+        assert!(!rule.matches(&distribution("Ubuntu", "ubuntu", "24.04", None)));
+        assert!(!rule.matches(&distribution("Debian", "debian", "12", store)));
+    }
+
+    #[test]
+    fn test_exclusion_rule() {
+        let rule: DistributionRule = "name != \"docker-desktop*\"".parse().unwrap();
+        assert_eq!(rule, !DistributionRule::name("docker-desktop*"));
+        assert!(!rule.matches(&distribution("docker-desktop-data", "other", "", None)));
+        assert!(rule.matches(&distribution("Ubuntu", "ubuntu", "24.04", None)));
+    }
+
+    #[test]
+    fn test_precedence_and_grouping() {
+        let rule: DistributionRule = "flavor = debian || flavor = ubuntu && !(version < 22.04)"
+            .parse()
+            .unwrap();
+        assert!(rule.matches(&distribution("Debian", "debian", "11", None)));
+        assert!(!rule.matches(&distribution("Ubuntu", "ubuntu", "20.04", None)));
+        let id_rule: DistributionRule = "id = {12345678-1234-1234-1234-123456789ABC}"
+            .parse()
+            .unwrap();
+        assert!(id_rule.matches(&distribution("Ubuntu", "ubuntu", "20.04", None)));
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        for text in [
+            "all",
+            "flavor = ubuntu && version >= 22.04 && packaged",
+            "name != \"docker desktop\" && (flavor = debian || package = Canonical*)",
+            "!(packaged || version < 3)",
+        ] {
+            let rule: DistributionRule = text.parse().unwrap();
+            assert_eq!(rule.to_string(), text);
+            assert_eq!(rule.to_string().parse::<DistributionRule>().unwrap(), rule);
+        }
+    }
+
+    #[test]
+    fn test_display_empty_groups() {
+        let ubuntu = distribution("Ubuntu", "ubuntu", "24.04", None);
+        for rule in [
+            DistributionRule::And(vec![]),
+            DistributionRule::Or(vec![]),
+            !DistributionRule::And(vec![]),
+            DistributionRule::Or(vec![
+                DistributionRule::And(vec![]),
+                DistributionRule::Packaged,
+            ]),
+        ] {
+            let parsed: DistributionRule = rule.to_string().parse().unwrap();
+            assert_eq!(parsed.matches(&ubuntu), rule.matches(&ubuntu), "{rule}");
+        }
+        assert_eq!(DistributionRule::Or(vec![]).to_string(), "!all");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            "flavor >= ubuntu"
+                .parse::<DistributionRule>()
+                .unwrap_err()
+                .offset(),
+            0
+        );
+        assert_eq!(
+            "name = x &&"
+                .parse::<DistributionRule>()
+                .unwrap_err()
+                .offset(),
+            11
+        );
+        assert!("(all".parse::<DistributionRule>().is_err());
+        assert_eq!(
+            "name = \"abc\\".parse::<DistributionRule>().unwrap_err(),
+            ParseDistributionRuleError::new(7, "unterminated string")
+        );
+        assert!("id = nope".parse::<DistributionRule>().is_err());
+        assert!("colour = red".parse::<DistributionRule>().is_err());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_rule_from_config_file() {
+        #[derive(serde::Deserialize)]
+        struct Config {
+            distributions: DistributionRule,
+        }
+        let config: Config =
+            toml::from_str(r#"distributions = 'name != "docker-desktop*"'"#).unwrap();
+        assert_eq!(
+            config.distributions,
+            !DistributionRule::name("docker-desktop*")
+        );
+    }
+}
@@ -0,0 +1,177 @@
+use crate::windows::core::{Error, HRESULT};
+use crate::windows::Win32::Foundation::{
+    ERROR_ALREADY_INITIALIZED, ERROR_INVALID_STATE, E_POINTER,
+};
+use crate::{require_version, WSLPluginAPIV1, WSLPluginHooksV1};
+use std::fmt;
+use std::sync::OnceLock;
+
+// A copy of the table: it only holds the version and function pointers, which stay valid
+// while the plugin is loaded, so it can be shared with any thread.
+static API: OnceLock<WSLPluginAPIV1> = OnceLock::new();
+
+/// Error returned by [`set_api`] when the API table was already stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApiAlreadySetError;
+
+impl fmt::Display for ApiAlreadySetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("the WSL plugin API table is already set")
+    }
+}
+
+impl std::error::Error for ApiAlreadySetError {}
+
+impl From<ApiAlreadySetError> for Error {
+    fn from(_: ApiAlreadySetError) -> Self {
+        Self::from_hresult(ERROR_ALREADY_INITIALIZED.to_hresult())
+    }
+}
+
+/// Stores the API table received at the entry point for the rest of the process.
+///
+/// The table can only be set once; later calls leave it unchanged.
+///
+/// # Safety
+///
+/// `api` must point to a valid `WSLPluginAPIV1`, such as the one received at the entry point
+/// or the table returned by [`InterposerBuilder::install`](crate::InterposerBuilder::install).
+pub unsafe fn set_api(
+    api: *const WSLPluginAPIV1,
+) -> Result<&'static WSLPluginAPIV1, ApiAlreadySetError> {
+    let mut stored = false;
+    let table = API.get_or_init(|| {
+        stored = true;
+        *api
+    });
+    if stored {
+        Ok(table)
+    } else {
+        Err(ApiAlreadySetError)
+    }
+}
+
+/// Returns the API table stored by [`set_api`], or `None` before the entry point ran.
+pub fn api() -> Option<&'static WSLPluginAPIV1> {
+    API.get()
+}
+
+/// Returns the API table, or `ERROR_INVALID_STATE` before the entry point ran.
+///
+/// Convenient in hooks, which report failures as an `HRESULT`.
+pub fn try_api() -> crate::windows::core::Result<&'static WSLPluginAPIV1> {
+    api().ok_or_else(|| Error::from_hresult(ERROR_INVALID_STATE.to_hresult()))
+}
+
+/// Returns the API table.
+///
+/// # Panics
+///
+/// Panics if called before the entry point stored the table.
+pub fn expect_api() -> &'static WSLPluginAPIV1 {
+    api().expect("the WSL plugin API was used before the plugin entry point ran")
+}
+
+/// Implements the body of a plugin entry point.
+///
+/// Checks that WSL provides at least the required version, stores `api` with [`set_api`],
+/// then lets `register` fill `hooks`. Returns `WSL_E_PLUGIN_REQUIRES_UPDATE` when WSL is too
+/// old, `E_POINTER` for a null argument, `ERROR_ALREADY_INITIALIZED` when the table was
+/// already stored, and otherwise the result of `register`.
+///
+/// # Safety
+///
+/// `api` and `hooks` must be the pointers received by the entry point.
+pub unsafe fn initialize_plugin<F>(
+    api: *const WSLPluginAPIV1,
+    hooks: *mut WSLPluginHooksV1,
+    required_major: u32,
+    required_minor: u32,
+    required_revision: u32,
+    register: F,
+) -> HRESULT
+where
+    F: FnOnce(&'static WSLPluginAPIV1, &mut WSLPluginHooksV1) -> HRESULT,
+{
+    let Some(hooks) = hooks.as_mut() else {
+        return E_POINTER;
+    };
+    if api.is_null() {
+        return E_POINTER;
+    }
+    let result = require_version(required_major, required_minor, required_revision, api);
+    if result.is_err() {
+        return result;
+    }
+    match set_api(api) {
+        Ok(api) => register(api, hooks),
+        Err(error) => Error::from(error).code(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::api_table as table;
+    use crate::windows::Win32::Foundation::S_OK;
+    use crate::WSL_E_PLUGIN_REQUIRES_UPDATE;
+
+    fn empty_hooks() -> WSLPluginHooksV1 {
+        WSLPluginHooksV1 {
+            OnVMStarted: None,
+            OnVMStopping: None,
+            OnDistributionStarted: None,
+            OnDistributionStopping: None,
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
+            OnDistributionRegistered: None,
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
+            OnDistributionUnregistered: None,
+        }
+    }
+
+    #[test]
+    fn test_version_too_old() {
+        let old = table(2, 0, 9);
+        let mut hooks = empty_hooks();
+        let result = unsafe {
+            initialize_plugin(&old, &mut hooks, 2, 1, 2, |_, _| {
+                panic!("hooks must not be registered")
+            })
+        };
+        assert_eq!(result, WSL_E_PLUGIN_REQUIRES_UPDATE);
+        let result = unsafe { initialize_plugin(&old, std::ptr::null_mut(), 2, 0, 0, |_, _| S_OK) };
+        assert_eq!(result, E_POINTER);
+    }
+
+    // The table is process-wide, so its whole life cycle is covered by a single test.
+    #[test]
+    fn test_global_api() {
+        assert!(api().is_none());
+        assert_eq!(
+            try_api().unwrap_err().code(),
+            ERROR_INVALID_STATE.to_hresult()
+        );
+        let received = table(2, 4, 4);
+        let mut hooks = empty_hooks();
+        let result = unsafe {
+            initialize_plugin(&received, &mut hooks, 2, 1, 2, |api, _| {
+                assert_eq!(api.Version.Minor, 4);
+                S_OK
+            })
+        };
+        assert_eq!(result, S_OK);
+        let from_thread = std::thread::spawn(|| expect_api().Version.Revision)
+            .join()
+            .unwrap();
+        assert_eq!(from_thread, 4);
+        assert_eq!(
+            unsafe { initialize_plugin(&received, &mut hooks, 2, 1, 2, |_, _| S_OK) },
+            ERROR_ALREADY_INITIALIZED.to_hresult()
+        );
+        assert_eq!(
+            unsafe { set_api(&table(3, 0, 0)) }.unwrap_err(),
+            ApiAlreadySetError
+        );
+        assert_eq!(try_api().unwrap().Version.Major, 2);
+    }
+}
@@ -0,0 +1,53 @@
+use crate::manual::make_hresult;
+use crate::windows::core::HRESULT;
+use crate::windows::Win32::Foundation::SEVERITY_ERROR;
+use crate::windows::Win32::System::Diagnostics::Debug::FACILITY_ITF;
+
+include!(env!("WSL_PLUGIN_API_MACROS_FILE_PATH"));
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::api_table;
+    use crate::{wsl_plugin_require_version, WSLPluginAPIV1};
+
+    fn rust_require_version(
+        major: u32,
+        minor: u32,
+        revision: u32,
+        api: &WSLPluginAPIV1,
+    ) -> HRESULT {
+        unsafe { wsl_plugin_require_version!(major, minor, revision, api) };
+        crate::windows::Win32::Foundation::S_OK
+    }
+
+    #[test]
+    fn test_entry_point_name() {
+        assert_eq!(WSLPLUGINAPI_ENTRYPOINTV1, "WSLPluginAPIV1_EntryPoint");
+        assert_eq!(
+            WSLPLUGINAPI_ENTRYPOINTV1_CSTR.to_str(),
+            Ok(WSLPLUGINAPI_ENTRYPOINTV1)
+        );
+    }
+
+    // Fails when the header changes what WSL_PLUGIN_REQUIRE_VERSION accepts.
+    #[test]
+    fn test_require_version_matches_header() {
+        let required: [(u32, u32, u32); 3] = [(1, 0, 0), (2, 1, 2), (2, 4, 4)];
+        for (major, minor, revision) in required {
+            for api_major in major.saturating_sub(1)..=major + 1 {
+                for api_minor in minor.saturating_sub(1)..=minor + 1 {
+                    for api_revision in revision.saturating_sub(1)..=revision + 1 {
+                        let api = api_table(api_major, api_minor, api_revision);
+                        assert_eq!(
+                            rust_require_version(major, minor, revision, &api),
+                            header_require_version(major, minor, revision, &api),
+                            "requiring {major}.{minor}.{revision} from \
+                             {api_major}.{api_minor}.{api_revision}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,551 @@
+use crate::panics::panic_message;
+use crate::windows::core::HRESULT;
+use crate::windows::Win32::Foundation::{E_POINTER, E_UNEXPECTED, S_OK};
+#[cfg(wsl_plugin_api_ge_2_1_2)]
+use crate::WslOfflineDistributionInformation;
+use crate::{
+    WSLDistributionInformation, WSLPluginHooksV1, WSLSessionInformation, WSLVmCreationSettings,
+};
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Arc, OnceLock};
+
+/// One feature of a plugin, with its own handlers for the hooks of `WSLPluginHooksV1`.
+///
+/// Handlers default to doing nothing. The references are only valid during the call.
+pub trait PluginModule: Send + Sync {
+    /// Name used in logs.
+    fn name(&self) -> &str;
+
+    fn on_vm_started(
+        &self,
+        session: &WSLSessionInformation,
+        settings: &WSLVmCreationSettings,
+    ) -> HRESULT {
+        let _ = (session, settings);
+        S_OK
+    }
+
+    fn on_vm_stopping(&self, session: &WSLSessionInformation) -> HRESULT {
+        let _ = session;
+        S_OK
+    }
+
+    fn on_distribution_started(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &WSLDistributionInformation,
+    ) -> HRESULT {
+        let _ = (session, distribution);
+        S_OK
+    }
+
+    fn on_distribution_stopping(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &WSLDistributionInformation,
+    ) -> HRESULT {
+        let _ = (session, distribution);
+        S_OK
+    }
+
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    fn on_distribution_registered(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &WslOfflineDistributionInformation,
+    ) -> HRESULT {
+        let _ = (session, distribution);
+        S_OK
+    }
+
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    fn on_distribution_unregistered(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &WslOfflineDistributionInformation,
+    ) -> HRESULT {
+        let _ = (session, distribution);
+        S_OK
+    }
+}
+
+impl<T: PluginModule + ?Sized> PluginModule for Arc<T> {
+    fn name(&self) -> &str {
+        (**self).name()
+    }
+
+    fn on_vm_started(
+        &self,
+        session: &WSLSessionInformation,
+        settings: &WSLVmCreationSettings,
+    ) -> HRESULT {
+        (**self).on_vm_started(session, settings)
+    }
+
+    fn on_vm_stopping(&self, session: &WSLSessionInformation) -> HRESULT {
+        (**self).on_vm_stopping(session)
+    }
+
+    fn on_distribution_started(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &WSLDistributionInformation,
+    ) -> HRESULT {
+        (**self).on_distribution_started(session, distribution)
+    }
+
+    fn on_distribution_stopping(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &WSLDistributionInformation,
+    ) -> HRESULT {
+        (**self).on_distribution_stopping(session, distribution)
+    }
+
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    fn on_distribution_registered(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &WslOfflineDistributionInformation,
+    ) -> HRESULT {
+        (**self).on_distribution_registered(session, distribution)
+    }
+
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    fn on_distribution_unregistered(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &WslOfflineDistributionInformation,
+    ) -> HRESULT {
+        (**self).on_distribution_unregistered(session, distribution)
+    }
+}
+
+/// A callback of `WSLPluginHooksV1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Hook {
+    VmStarted,
+    VmStopping,
+    DistributionStarted,
+    DistributionStopping,
+    DistributionRegistered,
+    DistributionUnregistered,
+}
+
+impl Hook {
+    const ALL: [Self; 6] = [
+        Self::VmStarted,
+        Self::VmStopping,
+        Self::DistributionStarted,
+        Self::DistributionStopping,
+        Self::DistributionRegistered,
+        Self::DistributionUnregistered,
+    ];
+
+    /// Whether modules see this hook in reverse registration order, so that what started
+    /// last is torn down first.
+    pub fn is_stopping(self) -> bool {
+        matches!(self, Self::VmStopping | Self::DistributionStopping)
+    }
+
+    fn default_policy(self) -> AggregationPolicy {
+        match self {
+            Self::VmStarted | Self::DistributionStarted => AggregationPolicy::FirstFailure,
+            Self::VmStopping | Self::DistributionStopping => AggregationPolicy::AllMustSucceed,
+            // WSL ignores the result of these hooks anyway.
+            Self::DistributionRegistered | Self::DistributionUnregistered => {
+                AggregationPolicy::IgnoreFailures
+            }
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+impl fmt::Display for Hook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::VmStarted => "OnVMStarted",
+            Self::VmStopping => "OnVMStopping",
+            Self::DistributionStarted => "OnDistributionStarted",
+            Self::DistributionStopping => "OnDistributionStopping",
+            Self::DistributionRegistered => "OnDistributionRegistered",
+            Self::DistributionUnregistered => "OnDistributionUnregistered",
+        })
+    }
+}
+
+/// How the results of the modules are combined into the result returned to WSL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AggregationPolicy {
+    /// Stops at the first failing module and returns its result.
+    FirstFailure,
+    /// Calls every module and returns the first failure, if any.
+    AllMustSucceed,
+    /// Calls every module and always succeeds; failures are only logged.
+    IgnoreFailures,
+}
+
+/// Combines several [`PluginModule`]s into one set of hooks.
+///
+/// Modules are called in registration order, or in reverse order for the stopping hooks. A
+/// panicking module is reported as failing with `E_UNEXPECTED` without affecting the others.
+/// Defaults: [`AggregationPolicy::FirstFailure`] for the started hooks,
+/// [`AggregationPolicy::AllMustSucceed`] for the stopping hooks and
+/// [`AggregationPolicy::IgnoreFailures`] for the registration hooks.
+pub struct HookMultiplexer {
+    modules: Vec<Box<dyn PluginModule>>,
+    policies: [AggregationPolicy; 6],
+}
+
+impl fmt::Debug for HookMultiplexer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("HookMultiplexer")
+            .field(
+                "modules",
+                &self.modules.iter().map(|m| m.name()).collect::<Vec<_>>(),
+            )
+            .field("policies", &self.policies)
+            .finish()
+    }
+}
+
+impl Default for HookMultiplexer {
+    fn default() -> Self {
+        Self {
+            modules: Vec::new(),
+            policies: Hook::ALL.map(Hook::default_policy),
+        }
+    }
+}
+
+// The hooks are plain C functions, so the state they use has to be global.
+static MULTIPLEXER: OnceLock<HookMultiplexer> = OnceLock::new();
+
+/// Error returned by [`HookMultiplexer::install`] when hooks are already installed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HooksAlreadyInstalledError;
+
+impl fmt::Display for HooksAlreadyInstalledError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("a hook multiplexer is already installed")
+    }
+}
+
+impl std::error::Error for HooksAlreadyInstalledError {}
+
+impl HookMultiplexer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a module after the ones already registered.
+    pub fn module(mut self, module: impl PluginModule + 'static) -> Self {
+        self.modules.push(Box::new(module));
+        self
+    }
+
+    pub fn policy(mut self, hook: Hook, policy: AggregationPolicy) -> Self {
+        self.policies[hook.index()] = policy;
+        self
+    }
+
+    /// Makes this multiplexer the process-wide one and points every hook of `hooks` at it.
+    pub fn install(
+        self,
+        hooks: &mut WSLPluginHooksV1,
+    ) -> Result<&'static Self, HooksAlreadyInstalledError> {
+        let mut installed = false;
+        let multiplexer = MULTIPLEXER.get_or_init(|| {
+            installed = true;
+            self
+        });
+        if !installed {
+            return Err(HooksAlreadyInstalledError);
+        }
+        hooks.OnVMStarted = Some(on_vm_started);
+        hooks.OnVMStopping = Some(on_vm_stopping);
+        hooks.OnDistributionStarted = Some(on_distribution_started);
+        hooks.OnDistributionStopping = Some(on_distribution_stopping);
+        #[cfg(wsl_plugin_api_ge_2_1_2)]
+        {
+            hooks.OnDistributionRegistered = Some(on_distribution_registered);
+            hooks.OnDistributionUnregistered = Some(on_distribution_unregistered);
+        }
+        Ok(multiplexer)
+    }
+
+    /// Calls `handler` on every module following the policy of `hook`.
+    pub fn dispatch<F>(&self, hook: Hook, handler: F) -> HRESULT
+    where
+        F: Fn(&dyn PluginModule) -> HRESULT,
+    {
+        let policy = self.policies[hook.index()];
+        let modules: Box<dyn Iterator<Item = &Box<dyn PluginModule>>> = if hook.is_stopping() {
+            Box::new(self.modules.iter().rev())
+        } else {
+            Box::new(self.modules.iter())
+        };
+        let mut first_failure = None;
+        for module in modules {
+            let result = match catch_unwind(AssertUnwindSafe(|| handler(module.as_ref()))) {
+                Ok(result) => result,
+                Err(payload) => {
+                    log::error!(
+                        "{hook} of module {} panicked: {}",
+                        module.name(),
+                        panic_message(payload.as_ref())
+                    );
+                    E_UNEXPECTED
+                }
+            };
+            if result.is_ok() {
+                continue;
+            }
+            log::warn!("{hook} of module {} failed: {result}", module.name());
+            first_failure.get_or_insert(result);
+            if policy == AggregationPolicy::FirstFailure {
+                break;
+            }
+        }
+        match (policy, first_failure) {
+            (AggregationPolicy::IgnoreFailures, _) | (_, None) => S_OK,
+            (_, Some(failure)) => failure,
+        }
+    }
+}
+
+fn dispatch_installed<F>(hook: Hook, handler: F) -> HRESULT
+where
+    F: Fn(&dyn PluginModule) -> HRESULT,
+{
+    // The hooks are only set by `install`, after the multiplexer is stored; panicking here
+    // would unwind into WSL.
+    match MULTIPLEXER.get() {
+        Some(multiplexer) => multiplexer.dispatch(hook, handler),
+        None => E_UNEXPECTED,
+    }
+}
+
+unsafe extern "C" fn on_vm_started(
+    session: *const WSLSessionInformation,
+    settings: *const WSLVmCreationSettings,
+) -> HRESULT {
+    let (Some(session), Some(settings)) = (session.as_ref(), settings.as_ref()) else {
+        return E_POINTER;
+    };
+    dispatch_installed(Hook::VmStarted, |module| {
+        module.on_vm_started(session, settings)
+    })
+}
+
+unsafe extern "C" fn on_vm_stopping(session: *const WSLSessionInformation) -> HRESULT {
+    let Some(session) = session.as_ref() else {
+        return E_POINTER;
+    };
+    dispatch_installed(Hook::VmStopping, |module| module.on_vm_stopping(session))
+}
+
+unsafe extern "C" fn on_distribution_started(
+    session: *const WSLSessionInformation,
+    distribution: *const WSLDistributionInformation,
+) -> HRESULT {
+    let (Some(session), Some(distribution)) = (session.as_ref(), distribution.as_ref()) else {
+        return E_POINTER;
+    };
+    dispatch_installed(Hook::DistributionStarted, |module| {
+        module.on_distribution_started(session, distribution)
+    })
+}
+
+unsafe extern "C" fn on_distribution_stopping(
+    session: *const WSLSessionInformation,
+    distribution: *const WSLDistributionInformation,
+) -> HRESULT {
+    let (Some(session), Some(distribution)) = (session.as_ref(), distribution.as_ref()) else {
+        return E_POINTER;
+    };
+    dispatch_installed(Hook::DistributionStopping, |module| {
+        module.on_distribution_stopping(session, distribution)
+    })
+}
+
+#[cfg(wsl_plugin_api_ge_2_1_2)]
+unsafe extern "C" fn on_distribution_registered(
+    session: *const WSLSessionInformation,
+    distribution: *const WslOfflineDistributionInformation,
+) -> HRESULT {
+    let (Some(session), Some(distribution)) = (session.as_ref(), distribution.as_ref()) else {
+        return E_POINTER;
+    };
+    dispatch_installed(Hook::DistributionRegistered, |module| {
+        module.on_distribution_registered(session, distribution)
+    })
+}
+
+#[cfg(wsl_plugin_api_ge_2_1_2)]
+unsafe extern "C" fn on_distribution_unregistered(
+    session: *const WSLSessionInformation,
+    distribution: *const WslOfflineDistributionInformation,
+) -> HRESULT {
+    let (Some(session), Some(distribution)) = (session.as_ref(), distribution.as_ref()) else {
+        return E_POINTER;
+    };
+    dispatch_installed(Hook::DistributionUnregistered, |module| {
+        module.on_distribution_unregistered(session, distribution)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::windows::Win32::Foundation::{E_ACCESSDENIED, E_FAIL, HANDLE};
+    use crate::windows::Win32::Security::PSID;
+    use std::sync::{Arc, Mutex};
+
+    type Calls = Arc<Mutex<Vec<String>>>;
+
+    struct Recorder {
+        name: &'static str,
+        result: HRESULT,
+        panics: bool,
+        calls: Calls,
+    }
+
+    impl Recorder {
+        fn new(name: &'static str, result: HRESULT, calls: &Calls) -> Self {
+            Self {
+                name,
+                result,
+                panics: false,
+                calls: calls.clone(),
+            }
+        }
+
+        fn record(&self) -> HRESULT {
+            self.calls.lock().unwrap().push(self.name.to_owned());
+            if self.panics {
+                panic!("{} is broken", self.name);
+            }
+            self.result
+        }
+    }
+
+    impl PluginModule for Recorder {
+        fn name(&self) -> &str {
+            self.name
+        }
+
+        fn on_vm_started(&self, _: &WSLSessionInformation, _: &WSLVmCreationSettings) -> HRESULT {
+            self.record()
+        }
+
+        fn on_vm_stopping(&self, _: &WSLSessionInformation) -> HRESULT {
+            self.record()
+        }
+    }
+
+    fn session() -> WSLSessionInformation {
+        WSLSessionInformation {
+            SessionId: 1,
+            UserToken: HANDLE::default(),
+            UserSid: PSID::default(),
+        }
+    }
+
+    fn settings() -> WSLVmCreationSettings {
+        WSLVmCreationSettings {
+            CustomConfigurationFlags: 0,
+        }
+    }
+
+    fn multiplexer(calls: &Calls, first: HRESULT, second: HRESULT) -> HookMultiplexer {
+        HookMultiplexer::new()
+            .module(Recorder::new("first", first, calls))
+            .module(Recorder::new("second", second, calls))
+            .module(Recorder::new("third", S_OK, calls))
+    }
+
+    fn take(calls: &Calls) -> Vec<String> {
+        std::mem::take(&mut *calls.lock().unwrap())
+    }
+
+    #[test]
+    fn test_policies() {
+        let calls = Calls::default();
+        let (session, settings) = (session(), settings());
+        let started = |multiplexer: &HookMultiplexer| {
+            multiplexer.dispatch(Hook::VmStarted, |m| m.on_vm_started(&session, &settings))
+        };
+        let first_failure = multiplexer(&calls, S_OK, E_ACCESSDENIED);
+        assert_eq!(started(&first_failure), E_ACCESSDENIED);
+        assert_eq!(take(&calls), ["first", "second"]);
+        let all = multiplexer(&calls, E_FAIL, E_ACCESSDENIED)
+            .policy(Hook::VmStarted, AggregationPolicy::AllMustSucceed);
+        assert_eq!(started(&all), E_FAIL);
+        assert_eq!(take(&calls), ["first", "second", "third"]);
+        let ignored = multiplexer(&calls, E_FAIL, E_FAIL)
+            .policy(Hook::VmStarted, AggregationPolicy::IgnoreFailures);
+        assert_eq!(started(&ignored), S_OK);
+        assert_eq!(take(&calls).len(), 3);
+    }
+
+    #[test]
+    fn test_stopping_order() {
+        let calls = Calls::default();
+        let session = session();
+        let multiplexer = multiplexer(&calls, E_FAIL, S_OK);
+        let result = multiplexer.dispatch(Hook::VmStopping, |m| m.on_vm_stopping(&session));
+        assert_eq!(result, E_FAIL);
+        assert_eq!(take(&calls), ["third", "second", "first"]);
+    }
+
+    #[test]
+    fn test_panic_isolation() {
+        let calls = Calls::default();
+        let broken = Recorder {
+            panics: true,
+            ..Recorder::new("broken", S_OK, &calls)
+        };
+        let multiplexer = HookMultiplexer::new()
+            .module(broken)
+            .module(Recorder::new("healthy", S_OK, &calls))
+            .policy(Hook::VmStarted, AggregationPolicy::AllMustSucceed);
+        let (session, settings) = (session(), settings());
+        let result =
+            multiplexer.dispatch(Hook::VmStarted, |m| m.on_vm_started(&session, &settings));
+        assert_eq!(result, E_UNEXPECTED);
+        assert_eq!(take(&calls), ["broken", "healthy"]);
+    }
+
+    #[test]
+    fn test_install() {
+        let calls = Calls::default();
+        let mut hooks = WSLPluginHooksV1 {
+            OnVMStarted: None,
+            OnVMStopping: None,
+            OnDistributionStarted: None,
+            OnDistributionStopping: None,
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
+            OnDistributionRegistered: None,
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
+            OnDistributionUnregistered: None,
+        };
+        multiplexer(&calls, S_OK, S_OK).install(&mut hooks).unwrap();
+        let (session, settings) = (session(), settings());
+        let result = unsafe { hooks.OnVMStarted.unwrap()(&session, &settings) };
+        assert_eq!(result, S_OK);
+        assert_eq!(take(&calls), ["first", "second", "third"]);
+        assert_eq!(
+            unsafe { hooks.OnVMStopping.unwrap()(std::ptr::null()) },
+            E_POINTER
+        );
+        assert_eq!(
+            HookMultiplexer::new().install(&mut hooks).unwrap_err(),
+            HooksAlreadyInstalledError
+        );
+    }
+}
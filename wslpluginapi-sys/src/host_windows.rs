@@ -0,0 +1,140 @@
+//! Stand-ins for the items of the `windows` crate this crate uses, for host-testing mode.
+//!
+//! The `windows` crate does not build for other operating systems, so on them the crate
+//! refers to `crate::windows` instead: `core` comes from `windows-core`, which does, and the
+//! Win32 types and constants are declared here with the same names, layouts and values.
+#![allow(dead_code, unused_imports, non_camel_case_types, non_snake_case)]
+#![allow(clippy::upper_case_acronyms)]
+
+pub mod core {
+    pub use windows_core::{Error, Result, BOOL, GUID, HRESULT};
+
+    /// A pointer to a constant null-terminated string of 16-bit Unicode characters.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct PCWSTR(pub *const u16);
+
+    impl PCWSTR {
+        pub const fn null() -> Self {
+            Self(::core::ptr::null())
+        }
+
+        pub const fn as_ptr(&self) -> *const u16 {
+            self.0
+        }
+
+        pub fn is_null(&self) -> bool {
+            self.0.is_null()
+        }
+
+        /// # Safety
+        ///
+        /// The pointer must point to a valid null-terminated string.
+        pub unsafe fn len(&self) -> usize {
+            let mut len = 0;
+            while *self.0.add(len) != 0 {
+                len += 1;
+            }
+            len
+        }
+
+        /// # Safety
+        ///
+        /// Same requirements as [`PCWSTR::len`], and the string must outlive the slice.
+        pub unsafe fn as_wide(&self) -> &[u16] {
+            ::core::slice::from_raw_parts(self.0, self.len())
+        }
+    }
+
+    /// A pointer to a constant null-terminated string of 8-bit characters.
+    #[repr(transparent)]
+    #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+    pub struct PCSTR(pub *const u8);
+
+    impl PCSTR {
+        pub const fn null() -> Self {
+            Self(::core::ptr::null())
+        }
+
+        pub const fn as_ptr(&self) -> *const u8 {
+            self.0
+        }
+
+        pub fn is_null(&self) -> bool {
+            self.0.is_null()
+        }
+    }
+}
+
+pub mod Win32 {
+    pub mod Foundation {
+        use crate::windows::core::HRESULT;
+
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub struct HANDLE(pub *mut ::core::ffi::c_void);
+
+        impl Default for HANDLE {
+            fn default() -> Self {
+                Self(::core::ptr::null_mut())
+            }
+        }
+
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+        pub struct WIN32_ERROR(pub u32);
+
+        impl WIN32_ERROR {
+            pub const fn to_hresult(self) -> HRESULT {
+                HRESULT::from_win32(self.0)
+            }
+        }
+
+        pub const SEVERITY_ERROR: u32 = 1u32;
+        pub const S_OK: HRESULT = HRESULT(0x0_u32 as _);
+        pub const S_FALSE: HRESULT = HRESULT(0x1_u32 as _);
+        pub const E_ACCESSDENIED: HRESULT = HRESULT(0x80070005_u32 as _);
+        pub const E_FAIL: HRESULT = HRESULT(0x80004005_u32 as _);
+        pub const E_INVALIDARG: HRESULT = HRESULT(0x80070057_u32 as _);
+        pub const E_NOTIMPL: HRESULT = HRESULT(0x80004001_u32 as _);
+        pub const E_POINTER: HRESULT = HRESULT(0x80004003_u32 as _);
+        pub const E_UNEXPECTED: HRESULT = HRESULT(0x8000FFFF_u32 as _);
+        pub const ERROR_ALREADY_EXISTS: WIN32_ERROR = WIN32_ERROR(183u32);
+        pub const ERROR_ALREADY_INITIALIZED: WIN32_ERROR = WIN32_ERROR(1247u32);
+        pub const ERROR_ENVVAR_NOT_FOUND: WIN32_ERROR = WIN32_ERROR(203u32);
+        pub const ERROR_INVALID_STATE: WIN32_ERROR = WIN32_ERROR(5023u32);
+        pub const ERROR_TIMEOUT: WIN32_ERROR = WIN32_ERROR(1460u32);
+    }
+
+    pub mod Security {
+        #[repr(transparent)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub struct PSID(pub *mut ::core::ffi::c_void);
+
+        impl Default for PSID {
+            fn default() -> Self {
+                Self(::core::ptr::null_mut())
+            }
+        }
+    }
+
+    pub mod Networking {
+        pub mod WinSock {
+            #[repr(transparent)]
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+            pub struct SOCKET(pub usize);
+        }
+    }
+
+    pub mod System {
+        pub mod Diagnostics {
+            pub mod Debug {
+                #[repr(transparent)]
+                #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+                pub struct FACILITY_CODE(pub u32);
+
+                pub const FACILITY_ITF: FACILITY_CODE = FACILITY_CODE(4u32);
+            }
+        }
+    }
+}
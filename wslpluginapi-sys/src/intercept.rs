@@ -0,0 +1,634 @@
+use crate::panics::panic_message;
+use crate::strings::read_wide_string;
+use crate::windows::core::{BOOL, GUID, HRESULT, PCSTR, PCWSTR};
+use crate::windows::Win32::Foundation::{E_FAIL, E_NOTIMPL, E_UNEXPECTED, S_OK};
+use crate::windows::Win32::Networking::WinSock::SOCKET;
+use crate::{WSLPluginAPIV1, WSLSessionId};
+use std::collections::VecDeque;
+use std::ffi::CStr;
+use std::fmt;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+use std::sync::{Mutex, MutexGuard, OnceLock, PoisonError};
+use std::time::{Duration, Instant};
+
+/// A call made by the plugin through the API table, with its arguments decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApiCall {
+    MountFolder {
+        session: WSLSessionId,
+        windows_path: String,
+        linux_path: String,
+        read_only: bool,
+        name: String,
+    },
+    ExecuteBinary {
+        session: WSLSessionId,
+        path: String,
+        arguments: Vec<String>,
+    },
+    ExecuteBinaryInDistribution {
+        session: WSLSessionId,
+        distribution: GUID,
+        path: String,
+        arguments: Vec<String>,
+    },
+    PluginError {
+        message: String,
+    },
+}
+
+impl ApiCall {
+    /// Name of the API function.
+    pub fn function(&self) -> &'static str {
+        match self {
+            Self::MountFolder { .. } => "MountFolder",
+            Self::ExecuteBinary { .. } => "ExecuteBinary",
+            Self::ExecuteBinaryInDistribution { .. } => "ExecuteBinaryInDistribution",
+            Self::PluginError { .. } => "PluginError",
+        }
+    }
+}
+
+impl fmt::Display for ApiCall {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MountFolder {
+                session,
+                windows_path,
+                linux_path,
+                read_only,
+                name,
+            } => write!(
+                f,
+                "MountFolder(session={session}, windows_path={windows_path:?}, \
+                 linux_path={linux_path:?}, read_only={read_only}, name={name:?})"
+            ),
+            Self::ExecuteBinary {
+                session,
+                path,
+                arguments,
+            } => write!(
+                f,
+                "ExecuteBinary(session={session}, path={path:?}, arguments={arguments:?})"
+            ),
+            Self::ExecuteBinaryInDistribution {
+                session,
+                distribution,
+                path,
+                arguments,
+            } => write!(
+                f,
+                "ExecuteBinaryInDistribution(session={session}, distribution={distribution:?}, \
+                 path={path:?}, arguments={arguments:?})"
+            ),
+            Self::PluginError { message } => write!(f, "PluginError(message={message:?})"),
+        }
+    }
+}
+
+/// What happened to an intercepted call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CallOutcome {
+    pub result: HRESULT,
+    pub elapsed: Duration,
+    /// Whether the call reached WSL, as opposed to being answered by an interceptor or by the
+    /// dry-run mode.
+    pub forwarded: bool,
+}
+
+/// Observes, and possibly answers, the calls going through an [`Interposer`].
+pub trait Interceptor: Send + Sync {
+    /// Called before the call is forwarded to WSL.
+    ///
+    /// Returning `Some` answers the call with that result without forwarding it, and skips the
+    /// interceptors registered after this one.
+    fn before(&self, call: &ApiCall) -> Option<HRESULT> {
+        let _ = call;
+        None
+    }
+
+    /// Called once the call completed, whoever answered it.
+    fn after(&self, call: &ApiCall, outcome: &CallOutcome) {
+        let _ = (call, outcome);
+    }
+}
+
+/// A call recorded by an [`AuditLog`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditRecord {
+    pub call: ApiCall,
+    pub outcome: CallOutcome,
+}
+
+/// Interceptor logging every call with its result and duration.
+///
+/// Calls go to the `log` crate, and the most recent ones are also kept in memory.
+#[derive(Debug)]
+pub struct AuditLog {
+    capacity: usize,
+    records: Mutex<VecDeque<AuditRecord>>,
+}
+
+impl AuditLog {
+    /// Creates a log keeping up to `capacity` records in memory.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Returns the records kept in memory, oldest first.
+    pub fn records(&self) -> Vec<AuditRecord> {
+        self.lock_records().iter().cloned().collect()
+    }
+
+    fn lock_records(&self) -> MutexGuard<'_, VecDeque<AuditRecord>> {
+        // A panic while pushing a record leaves the queue usable.
+        self.records.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Interceptor for AuditLog {
+    fn after(&self, call: &ApiCall, outcome: &CallOutcome) {
+        log::info!(
+            "{call} -> {:#010X} in {:?}{}",
+            outcome.result.0,
+            outcome.elapsed,
+            if outcome.forwarded {
+                ""
+            } else {
+                " (not forwarded)"
+            }
+        );
+        if self.capacity == 0 {
+            return;
+        }
+        let mut records = self.lock_records();
+        if records.len() == self.capacity {
+            records.pop_front();
+        }
+        records.push_back(AuditRecord {
+            call: call.clone(),
+            outcome: *outcome,
+        });
+    }
+}
+
+impl<T: Interceptor> Interceptor for std::sync::Arc<T> {
+    fn before(&self, call: &ApiCall) -> Option<HRESULT> {
+        (**self).before(call)
+    }
+
+    fn after(&self, call: &ApiCall, outcome: &CallOutcome) {
+        (**self).after(call, outcome)
+    }
+}
+
+/// Wraps the API table received from WSL with one going through Rust trampolines.
+///
+/// Plugin code keeps calling the table as usual; every call is decoded and passed to the
+/// interceptors. In dry-run mode nothing reaches WSL: calls succeed and binaries get a socket
+/// that is already at end of stream.
+pub struct Interposer {
+    original: WSLPluginAPIV1,
+    wrapped: WSLPluginAPIV1,
+    interceptors: Vec<Box<dyn Interceptor>>,
+    dry_run: bool,
+}
+
+impl fmt::Debug for Interposer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Interposer")
+            .field("original", &self.original)
+            .field("interceptors", &self.interceptors.len())
+            .field("dry_run", &self.dry_run)
+            .finish()
+    }
+}
+
+// The trampolines are plain C functions, so the state they use has to be global.
+static INTERPOSER: OnceLock<Interposer> = OnceLock::new();
+
+/// Error returned by [`InterposerBuilder::install`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlreadyInstalledError;
+
+impl fmt::Display for AlreadyInstalledError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("an interposer is already installed")
+    }
+}
+
+impl std::error::Error for AlreadyInstalledError {}
+
+/// Builds and installs the process-wide [`Interposer`].
+#[derive(Default)]
+pub struct InterposerBuilder {
+    interceptors: Vec<Box<dyn Interceptor>>,
+    dry_run: bool,
+}
+
+impl InterposerBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds an interceptor; interceptors run in the order they were added.
+    pub fn interceptor(mut self, interceptor: impl Interceptor + 'static) -> Self {
+        self.interceptors.push(Box::new(interceptor));
+        self
+    }
+
+    /// Records calls without forwarding them to WSL.
+    pub fn dry_run(mut self, dry_run: bool) -> Self {
+        self.dry_run = dry_run;
+        self
+    }
+
+    /// Installs the interposer around `api` and returns the table to hand to plugin code.
+    ///
+    /// Functions missing from `api` stay missing from the returned table, so version checks
+    /// keep working. Only one interposer can be installed per process.
+    ///
+    /// # Safety
+    ///
+    /// `api` must be the table received at the plugin entry point.
+    pub unsafe fn install(
+        self,
+        api: *const WSLPluginAPIV1,
+    ) -> Result<&'static WSLPluginAPIV1, AlreadyInstalledError> {
+        let original = *api;
+        let wrapped = WSLPluginAPIV1 {
+            Version: original.Version,
+            MountFolder: original.MountFolder.map(|_| mount_folder_trampoline as _),
+            ExecuteBinary: original
+                .ExecuteBinary
+                .map(|_| execute_binary_trampoline as _),
+            PluginError: original.PluginError.map(|_| plugin_error_trampoline as _),
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
+            ExecuteBinaryInDistribution: original
+                .ExecuteBinaryInDistribution
+                .map(|_| execute_binary_in_distribution_trampoline as _),
+        };
+        let mut installed = false;
+        let interposer = INTERPOSER.get_or_init(|| {
+            installed = true;
+            Interposer {
+                original,
+                wrapped,
+                interceptors: self.interceptors,
+                dry_run: self.dry_run,
+            }
+        });
+        if installed {
+            Ok(&interposer.wrapped)
+        } else {
+            Err(AlreadyInstalledError)
+        }
+    }
+}
+
+impl Interposer {
+    /// Returns the installed interposer, if any.
+    pub fn get() -> Option<&'static Self> {
+        INTERPOSER.get()
+    }
+
+    /// Returns the table received from WSL.
+    pub fn original(&self) -> &WSLPluginAPIV1 {
+        &self.original
+    }
+
+    /// Returns the table going through the interceptors.
+    pub fn wrapped(&self) -> &WSLPluginAPIV1 {
+        &self.wrapped
+    }
+
+    pub fn is_dry_run(&self) -> bool {
+        self.dry_run
+    }
+
+    fn dispatch(
+        &self,
+        call: &ApiCall,
+        dry_run: impl FnOnce() -> HRESULT,
+        forward: impl FnOnce() -> HRESULT,
+    ) -> HRESULT {
+        let start = Instant::now();
+        let answer = self
+            .interceptors
+            .iter()
+            .find_map(|interceptor| interceptor.before(call));
+        let (result, forwarded) = match answer {
+            Some(result) => (result, false),
+            None if self.dry_run => (dry_run(), false),
+            None => (forward(), true),
+        };
+        let outcome = CallOutcome {
+            result,
+            elapsed: start.elapsed(),
+            forwarded,
+        };
+        for interceptor in &self.interceptors {
+            interceptor.after(call, &outcome);
+        }
+        result
+    }
+}
+
+/// Runs a trampoline with the installed interposer.
+///
+/// Unwinding out of an `extern "C"` function aborts the WSL service, so a panic in an interceptor
+/// or while decoding the call is turned into `E_UNEXPECTED`.
+fn trampoline(function: &str, body: impl FnOnce(&'static Interposer) -> HRESULT) -> HRESULT {
+    // The trampolines are only reachable through the table returned by `install`.
+    let Some(interposer) = INTERPOSER.get() else {
+        return E_UNEXPECTED;
+    };
+    catch_unwind(AssertUnwindSafe(|| body(interposer))).unwrap_or_else(|payload| {
+        log::error!(
+            "{function} interposer panicked: {}",
+            panic_message(payload.as_ref())
+        );
+        E_UNEXPECTED
+    })
+}
+
+/// Creates a socket whose peer is already closed, standing for a binary that printed nothing.
+fn closed_process_socket() -> std::io::Result<SOCKET> {
+    #[cfg(windows)]
+    {
+        use std::net::{TcpListener, TcpStream};
+        use std::os::windows::io::IntoRawSocket;
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let stream = TcpStream::connect(listener.local_addr()?)?;
+        drop(listener.accept()?);
+        Ok(SOCKET(stream.into_raw_socket() as usize))
+    }
+    #[cfg(unix)]
+    {
+        use std::os::fd::IntoRawFd;
+        let (stream, _) = std::os::unix::net::UnixStream::pair()?;
+        Ok(SOCKET(stream.into_raw_fd() as usize))
+    }
+}
+
+unsafe fn read_string(s: PCSTR) -> String {
+    if s.is_null() {
+        String::new()
+    } else {
+        CStr::from_ptr(s.0.cast()).to_string_lossy().into_owned()
+    }
+}
+
+unsafe fn read_arguments(mut arguments: *mut PCSTR) -> Vec<String> {
+    let mut decoded = Vec::new();
+    if arguments.is_null() {
+        return decoded;
+    }
+    while !(*arguments).is_null() {
+        decoded.push(read_string(*arguments));
+        arguments = arguments.add(1);
+    }
+    decoded
+}
+
+unsafe fn dry_run_execute(socket: *mut SOCKET) -> HRESULT {
+    match closed_process_socket() {
+        Ok(closed) => {
+            *socket = closed;
+            S_OK
+        }
+        Err(_) => E_FAIL,
+    }
+}
+
+unsafe extern "C" fn mount_folder_trampoline(
+    session: WSLSessionId,
+    windows_path: PCWSTR,
+    linux_path: PCWSTR,
+    read_only: BOOL,
+    name: PCWSTR,
+) -> HRESULT {
+    trampoline("MountFolder", |interposer| {
+        let call = ApiCall::MountFolder {
+            session,
+            windows_path: read_wide_string(windows_path).unwrap_or_default(),
+            linux_path: read_wide_string(linux_path).unwrap_or_default(),
+            read_only: read_only.as_bool(),
+            name: read_wide_string(name).unwrap_or_default(),
+        };
+        interposer.dispatch(
+            &call,
+            || S_OK,
+            || match interposer.original.MountFolder {
+                Some(mount_folder) => {
+                    mount_folder(session, windows_path, linux_path, read_only, name)
+                }
+                None => E_NOTIMPL,
+            },
+        )
+    })
+}
+
+unsafe extern "C" fn execute_binary_trampoline(
+    session: WSLSessionId,
+    path: PCSTR,
+    arguments: *mut PCSTR,
+    socket: *mut SOCKET,
+) -> HRESULT {
+    trampoline("ExecuteBinary", |interposer| {
+        let call = ApiCall::ExecuteBinary {
+            session,
+            path: read_string(path),
+            arguments: read_arguments(arguments),
+        };
+        interposer.dispatch(
+            &call,
+            || dry_run_execute(socket),
+            || match interposer.original.ExecuteBinary {
+                Some(execute_binary) => execute_binary(session, path, arguments, socket),
+                None => E_NOTIMPL,
+            },
+        )
+    })
+}
+
+#[cfg(wsl_plugin_api_ge_2_1_2)]
+unsafe extern "C" fn execute_binary_in_distribution_trampoline(
+    session: WSLSessionId,
+    distribution: *const GUID,
+    path: PCSTR,
+    arguments: *mut PCSTR,
+    socket: *mut SOCKET,
+) -> HRESULT {
+    trampoline("ExecuteBinaryInDistribution", |interposer| {
+        let call = ApiCall::ExecuteBinaryInDistribution {
+            session,
+            distribution: distribution.as_ref().copied().unwrap_or_default(),
+            path: read_string(path),
+            arguments: read_arguments(arguments),
+        };
+        interposer.dispatch(
+            &call,
+            || dry_run_execute(socket),
+            || match interposer.original.ExecuteBinaryInDistribution {
+                Some(execute_binary) => {
+                    execute_binary(session, distribution, path, arguments, socket)
+                }
+                None => E_NOTIMPL,
+            },
+        )
+    })
+}
+
+unsafe extern "C" fn plugin_error_trampoline(message: PCWSTR) -> HRESULT {
+    trampoline("PluginError", |interposer| {
+        let call = ApiCall::PluginError {
+            message: read_wide_string(message).unwrap_or_default(),
+        };
+        interposer.dispatch(
+            &call,
+            || S_OK,
+            || match interposer.original.PluginError {
+                Some(plugin_error) => plugin_error(message),
+                None => E_NOTIMPL,
+            },
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::api_table;
+    use crate::windows::Win32::Foundation::{E_ACCESSDENIED, E_INVALIDARG};
+    use crate::{mount_folder, plugin_error};
+    use std::sync::Arc;
+
+    fn interposer(interceptors: Vec<Box<dyn Interceptor>>, dry_run: bool) -> Interposer {
+        let table = api_table(2, 4, 4);
+        Interposer {
+            original: table,
+            wrapped: table,
+            interceptors,
+            dry_run,
+        }
+    }
+
+    fn plugin_error_call() -> ApiCall {
+        ApiCall::PluginError {
+            message: "failed".into(),
+        }
+    }
+
+    struct Deny;
+
+    struct PanicOn(&'static str);
+
+    impl Interceptor for PanicOn {
+        fn before(&self, call: &ApiCall) -> Option<HRESULT> {
+            match call {
+                ApiCall::PluginError { message } if message == self.0 => panic!("{message}"),
+                _ => None,
+            }
+        }
+    }
+
+    impl Interceptor for Deny {
+        fn before(&self, call: &ApiCall) -> Option<HRESULT> {
+            matches!(call, ApiCall::PluginError { .. }).then_some(E_ACCESSDENIED)
+        }
+    }
+
+    #[test]
+    fn test_dispatch() {
+        let audit = Arc::new(AuditLog::new(2));
+        let interposer = interposer(vec![Box::new(audit.clone())], false);
+        let result = interposer.dispatch(&plugin_error_call(), || S_OK, || E_INVALIDARG);
+        assert_eq!(result, E_INVALIDARG);
+        let records = audit.records();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].call, plugin_error_call());
+        assert_eq!(records[0].outcome.result, E_INVALIDARG);
+        assert!(records[0].outcome.forwarded);
+    }
+
+    #[test]
+    fn test_dry_run_and_short_circuit() {
+        let audit = Arc::new(AuditLog::new(2));
+        let interposer = interposer(vec![Box::new(Deny), Box::new(audit.clone())], true);
+        let mount = ApiCall::MountFolder {
+            session: 1,
+            windows_path: r"C:\data".into(),
+            linux_path: "/mnt/data".into(),
+            read_only: true,
+            name: "data".into(),
+        };
+        let forward = || panic!("dry-run calls must not be forwarded");
+        assert_eq!(interposer.dispatch(&mount, || S_OK, forward), S_OK);
+        assert_eq!(
+            interposer.dispatch(&plugin_error_call(), || S_OK, forward),
+            E_ACCESSDENIED
+        );
+        assert_eq!(interposer.dispatch(&mount, || S_OK, forward), S_OK);
+        let records = audit.records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].outcome.result, E_ACCESSDENIED);
+        assert!(records.iter().all(|record| !record.outcome.forwarded));
+    }
+
+    unsafe extern "C" fn failing_plugin_error(_message: PCWSTR) -> HRESULT {
+        E_INVALIDARG
+    }
+
+    #[test]
+    fn test_install() {
+        let audit = Arc::new(AuditLog::new(8));
+        let api = WSLPluginAPIV1 {
+            PluginError: Some(failing_plugin_error),
+            ..api_table(2, 4, 4)
+        };
+        let wrapped = unsafe {
+            InterposerBuilder::new()
+                .interceptor(PanicOn("panic"))
+                .interceptor(audit.clone())
+                .install(&api)
+        }
+        .unwrap();
+        assert!(wrapped.MountFolder.is_none());
+        assert_eq!(
+            unsafe { mount_folder(wrapped, 1, "C:\\", "/mnt", false, "c") },
+            E_NOTIMPL
+        );
+        assert_eq!(unsafe { plugin_error(wrapped, "failed") }, E_INVALIDARG);
+        assert_eq!(audit.records()[0].call, plugin_error_call());
+        // The interceptors after the one that panicked are not called.
+        assert_eq!(unsafe { plugin_error(wrapped, "panic") }, E_UNEXPECTED);
+        assert_eq!(audit.records().len(), 1);
+        assert_eq!(
+            unsafe { InterposerBuilder::new().install(&api) }.unwrap_err(),
+            AlreadyInstalledError
+        );
+    }
+
+    #[test]
+    fn test_poisoned_audit_log() {
+        let audit = Arc::new(AuditLog::new(2));
+        let poisoner = audit.clone();
+        std::thread::spawn(move || {
+            let _records = poisoner.records.lock().unwrap();
+            panic!("poisoning the audit log");
+        })
+        .join()
+        .unwrap_err();
+        let outcome = CallOutcome {
+            result: S_OK,
+            elapsed: Duration::ZERO,
+            forwarded: true,
+        };
+        audit.after(&plugin_error_call(), &outcome);
+        assert_eq!(audit.records().len(), 1);
+    }
+}
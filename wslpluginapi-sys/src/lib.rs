@@ -1,5 +1,78 @@
 #![cfg_attr(docsrs, feature(doc_auto_cfg))]
+#[cfg(test)]
+mod abi_conformance;
+#[cfg(all(feature = "agent", wsl_plugin_api_ge_2_1_2))]
+mod agent;
+mod api_version;
 mod bindgen;
+#[cfg(test)]
+#[path = "../build/target.rs"]
+#[allow(dead_code)]
+mod build_target;
+#[cfg(feature = "calls")]
+mod calls;
+#[cfg(feature = "distribution")]
+mod distribution;
+#[cfg(feature = "distribution-rule")]
+mod distribution_rule;
+#[cfg(feature = "global-api")]
+mod global_api;
+mod header_macros;
+#[cfg(feature = "hook-multiplexer")]
+mod hook_multiplexer;
+#[cfg(feature = "intercept")]
+mod intercept;
 mod manual;
+#[cfg(feature = "mount-plan")]
+mod mount_plan;
+#[cfg(any(feature = "intercept", feature = "hook-multiplexer"))]
+mod panics;
+#[cfg(feature = "policy")]
+mod policy;
+#[cfg(feature = "startup-commands")]
+mod startup_commands;
+#[cfg(any(feature = "calls", feature = "distribution", feature = "intercept"))]
+mod strings;
+#[cfg(feature = "task-executor")]
+mod task_executor;
+#[cfg(test)]
+mod test_support;
+#[cfg(all(feature = "transfer", wsl_plugin_api_ge_2_1_2))]
+mod transfer;
+#[cfg(not(windows))]
+#[path = "host_windows.rs"]
+mod windows;
+#[cfg(feature = "wsl-version-output")]
+mod wsl_version_output;
 pub use crate::bindgen::*;
+#[cfg(windows)]
+use ::windows;
+#[cfg(all(feature = "agent", wsl_plugin_api_ge_2_1_2))]
+pub use agent::*;
+pub use api_version::*;
+#[cfg(feature = "calls")]
+pub use calls::*;
+#[cfg(feature = "distribution")]
+pub use distribution::*;
+#[cfg(feature = "distribution-rule")]
+pub use distribution_rule::*;
+#[cfg(feature = "global-api")]
+pub use global_api::*;
+pub use header_macros::*;
+#[cfg(feature = "hook-multiplexer")]
+pub use hook_multiplexer::*;
+#[cfg(feature = "intercept")]
+pub use intercept::*;
 pub use manual::*;
+#[cfg(feature = "mount-plan")]
+pub use mount_plan::*;
+#[cfg(feature = "policy")]
+pub use policy::*;
+#[cfg(feature = "startup-commands")]
+pub use startup_commands::*;
+#[cfg(feature = "task-executor")]
+pub use task_executor::*;
+#[cfg(all(feature = "transfer", wsl_plugin_api_ge_2_1_2))]
+pub use transfer::*;
+#[cfg(feature = "wsl-version-output")]
+pub use wsl_version_output::*;
@@ -1,16 +1,14 @@
-use crate::WSLPluginAPIV1;
-use windows::core::HRESULT;
-use windows::Win32::Foundation::{SEVERITY_ERROR, S_OK};
-use windows::Win32::System::Diagnostics::Debug::{FACILITY_CODE, FACILITY_ITF};
+use crate::windows::core::HRESULT;
+use crate::windows::Win32::Foundation::S_OK;
+use crate::windows::Win32::System::Diagnostics::Debug::FACILITY_CODE;
+use crate::{WSLPluginAPIV1, WSL_E_PLUGIN_REQUIRES_UPDATE};
 
+/// The `MAKE_HRESULT` macro of the Windows headers.
 #[inline(always)]
-const fn make_hresult(severity: u32, facility: FACILITY_CODE, code: u32) -> HRESULT {
+pub(crate) const fn make_hresult(severity: u32, facility: FACILITY_CODE, code: u32) -> HRESULT {
     HRESULT(((severity << 31) | (facility.0 << 16) | code) as i32)
 }
 
-pub const WSL_E_PLUGIN_REQUIRES_UPDATE: HRESULT =
-    make_hresult(SEVERITY_ERROR, FACILITY_ITF, 0x8004032A);
-
 /// Ensures the WSL Plugin API version meets the minimum required version.
 ///
 /// This function compares the version of the API passed as a parameter against the required
@@ -56,11 +54,47 @@ pub const unsafe fn require_version(
     }
 }
 
+/// Returns `WSL_E_PLUGIN_REQUIRES_UPDATE` from the enclosing function when the API version is
+/// lower than `major.minor.revision`, like the `WSL_PLUGIN_REQUIRE_VERSION` macro of the header.
+///
+/// `api` is a `*const WSLPluginAPIV1` (references coerce), and the enclosing function must
+/// return an `HRESULT`.
+///
+/// # Safety
+///
+/// The macro calls [`require_version`], so it must be used in an `unsafe` context with the
+/// same requirements on `api`.
+///
+// The example uses the `windows` crate, which host-testing builds do not have.
+#[cfg_attr(windows, doc = "```no_run")]
+#[cfg_attr(not(windows), doc = "```ignore")]
+/// use wslpluginapi_sys::{wsl_plugin_require_version, WSLPluginAPIV1, WSLPluginHooksV1};
+/// use windows::core::HRESULT;
+/// use windows::Win32::Foundation::S_OK;
+///
+/// #[no_mangle]
+/// pub unsafe extern "C" fn WSLPluginAPIV1_EntryPoint(
+///     api: *const WSLPluginAPIV1,
+///     hooks: *mut WSLPluginHooksV1,
+/// ) -> HRESULT {
+///     wsl_plugin_require_version!(2, 1, 2, api);
+///     S_OK
+/// }
+/// ```
+#[macro_export]
+macro_rules! wsl_plugin_require_version {
+    ($major:expr, $minor:expr, $revision:expr, $api:expr $(,)?) => {
+        if $crate::require_version($major, $minor, $revision, $api).is_err() {
+            return $crate::WSL_E_PLUGIN_REQUIRES_UPDATE;
+        }
+    };
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::windows::Win32::Foundation::S_OK;
     use crate::{WSLPluginAPIV1, WSLVersion};
-    use windows::Win32::Foundation::S_OK;
 
     #[test]
     fn test_version_exact_match() {
@@ -73,6 +107,7 @@ mod tests {
             MountFolder: None,
             ExecuteBinary: None,
             PluginError: None,
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
             ExecuteBinaryInDistribution: None,
         };
 
@@ -90,6 +125,7 @@ mod tests {
             MountFolder: None,
             ExecuteBinary: None,
             PluginError: None,
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
             ExecuteBinaryInDistribution: None,
         };
 
@@ -110,6 +146,7 @@ mod tests {
             MountFolder: None,
             ExecuteBinary: None,
             PluginError: None,
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
             ExecuteBinaryInDistribution: None,
         };
 
@@ -130,6 +167,7 @@ mod tests {
             MountFolder: None,
             ExecuteBinary: None,
             PluginError: None,
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
             ExecuteBinaryInDistribution: None,
         };
 
@@ -150,6 +188,7 @@ mod tests {
             MountFolder: None,
             ExecuteBinary: None,
             PluginError: None,
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
             ExecuteBinaryInDistribution: None,
         };
 
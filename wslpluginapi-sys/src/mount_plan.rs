@@ -0,0 +1,758 @@
+use crate::windows::core::HRESULT;
+use crate::windows::Win32::Foundation::{
+    ERROR_ALREADY_EXISTS, ERROR_ENVVAR_NOT_FOUND, S_FALSE, S_OK,
+};
+use crate::{
+    mount_folder, DistributionRule, GlobPattern, WSLDistributionInformation, WSLPluginAPIV1,
+    WSLSessionId, WSLSessionInformation,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+
+/// Resolves the `%NAME%` variables used in mount paths for a given session.
+pub trait PathVariableResolver {
+    /// Returns the value of `name` for the user of `session`, or `None` if it is unknown.
+    fn resolve(&self, session: &WSLSessionInformation, name: &str) -> Option<String>;
+}
+
+impl<F: Fn(&WSLSessionInformation, &str) -> Option<String>> PathVariableResolver for F {
+    fn resolve(&self, session: &WSLSessionInformation, name: &str) -> Option<String> {
+        self(session, name)
+    }
+}
+
+/// Resolves variables from the environment of the plugin process.
+///
+/// WSL plugins run inside the WSL service, so this is the environment of the service, not the
+/// one of the session's user.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ProcessEnvironmentResolver;
+
+impl PathVariableResolver for ProcessEnvironmentResolver {
+    fn resolve(&self, _session: &WSLSessionInformation, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+/// Resolves variables from the environment of the session's user (`%USERPROFILE%`,
+/// `%USERNAME%`, `%LOCALAPPDATA%`, ...) through its token.
+#[cfg(windows)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SessionUserEnvironmentResolver;
+
+#[cfg(windows)]
+impl PathVariableResolver for SessionUserEnvironmentResolver {
+    fn resolve(&self, session: &WSLSessionInformation, name: &str) -> Option<String> {
+        use crate::strings::to_wide_string;
+        use crate::windows::core::PCWSTR;
+        use crate::windows::Win32::System::Environment::ExpandEnvironmentStringsForUserW;
+        let variable = format!("%{name}%");
+        let source = to_wide_string(&variable);
+        let mut buffer = vec![0u16; 32 * 1024];
+        unsafe {
+            ExpandEnvironmentStringsForUserW(
+                Some(session.UserToken),
+                PCWSTR(source.as_ptr()),
+                &mut buffer,
+            )
+        }
+        .ok()?;
+        let len = buffer.iter().position(|c| *c == 0).unwrap_or(buffer.len());
+        let value = String::from_utf16_lossy(&buffer[..len]);
+        // Unknown variables are left untouched by the expansion.
+        (value != variable).then_some(value)
+    }
+}
+
+/// Error returned when a path references a variable the resolver does not know.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnresolvedVariableError(pub String);
+
+impl fmt::Display for UnresolvedVariableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unresolved variable %{}%", self.0)
+    }
+}
+
+impl std::error::Error for UnresolvedVariableError {}
+
+/// Expands the `%NAME%` variables of `template` for `session`. `%%` stands for a literal `%`.
+pub fn expand_path<R: PathVariableResolver + ?Sized>(
+    template: &str,
+    session: &WSLSessionInformation,
+    resolver: &R,
+) -> Result<String, UnresolvedVariableError> {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('%') {
+        expanded.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('%') {
+            Some(0) => {
+                expanded.push('%');
+                rest = &after[1..];
+            }
+            Some(end) => {
+                let name = &after[..end];
+                let value = resolver
+                    .resolve(session, name)
+                    .ok_or_else(|| UnresolvedVariableError(name.to_owned()))?;
+                expanded.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                expanded.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// A folder shared from Windows into the VM with `WSLPluginAPI_MountFolder`.
+///
+/// Mounts without `distributions` are applied when the VM starts, the others the first time a
+/// matching distribution starts in the session. `users` restricts the mount to sessions whose
+/// `%USERNAME%` matches one of the patterns; an empty list matches every session.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MountSpec {
+    pub name: String,
+    pub windows_path: String,
+    pub linux_path: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub read_only: bool,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub distributions: Option<DistributionRule>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub users: Vec<GlobPattern>,
+}
+
+impl MountSpec {
+    pub fn new<N: Into<String>, W: Into<String>, L: Into<String>>(
+        name: N,
+        windows_path: W,
+        linux_path: L,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            windows_path: windows_path.into(),
+            linux_path: linux_path.into(),
+            read_only: false,
+            distributions: None,
+            users: Vec::new(),
+        }
+    }
+
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Defers the mount until a distribution matching `rule` starts.
+    pub fn for_distributions(mut self, rule: DistributionRule) -> Self {
+        self.distributions = Some(rule);
+        self
+    }
+
+    /// Restricts the mount to the users matching `pattern`.
+    pub fn for_user<P: Into<GlobPattern>>(mut self, pattern: P) -> Self {
+        self.users.push(pattern.into());
+        self
+    }
+}
+
+/// Error returned when a mount plan is inconsistent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MountPlanError {
+    EmptyField { name: String, field: &'static str },
+    RelativeLinuxPath { name: String },
+    DuplicateName(String),
+    DuplicateLinuxPath { first: String, second: String },
+}
+
+impl fmt::Display for MountPlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EmptyField { name, field } => write!(f, "mount {name:?} has an empty {field}"),
+            Self::RelativeLinuxPath { name } => {
+                write!(f, "mount {name:?} has a relative Linux path")
+            }
+            Self::DuplicateName(name) => write!(f, "mount name {name:?} is used more than once"),
+            Self::DuplicateLinuxPath { first, second } => write!(
+                f,
+                "mounts {first:?} and {second:?} target the same Linux path"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MountPlanError {}
+
+/// Why a mount selected by a hook was not attempted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MountSkip {
+    /// The user of the session, `%USERNAME%`, matches none of the `users` patterns.
+    OtherUser(String),
+    /// `%USERNAME%` could not be resolved to check the `users` patterns.
+    UnknownUser,
+}
+
+impl fmt::Display for MountSkip {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OtherUser(user) => write!(f, "not mounted for user {user:?}"),
+            Self::UnknownUser => f.write_str("the user of the session is unknown"),
+        }
+    }
+}
+
+/// Result of a single mount attempt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MountOutcome {
+    pub name: String,
+    /// The expanded Windows path, `None` if expansion failed or the mount was skipped.
+    pub windows_path: Option<String>,
+    /// The expanded Linux path, `None` if expansion failed or the mount was skipped.
+    pub linux_path: Option<String>,
+    /// Set when the mount does not apply to the session and was not attempted.
+    pub skipped: Option<MountSkip>,
+    /// `S_OK` when mounted, `S_FALSE` when skipped or when the plan already mounted it in this
+    /// session, or the failure (for example `ERROR_ALREADY_EXISTS` when the name or path is
+    /// taken).
+    ///
+    /// A mount another hook is still creating counts as already mounted: `S_FALSE` is returned
+    /// without waiting for that hook, whose own outcome reports whether `MountFolder`
+    /// succeeded.
+    pub result: HRESULT,
+}
+
+/// Per-mount results of applying a [`MountPlan`] in a hook.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MountReport {
+    pub outcomes: Vec<MountOutcome>,
+}
+
+impl MountReport {
+    /// Returns `true` if no mount failed.
+    pub fn is_success(&self) -> bool {
+        self.outcomes.iter().all(|outcome| outcome.result.is_ok())
+    }
+
+    /// Returns the first failure, or `S_OK`.
+    pub fn result(&self) -> HRESULT {
+        self.outcomes
+            .iter()
+            .map(|outcome| outcome.result)
+            .find(|result| result.is_err())
+            .unwrap_or(S_OK)
+    }
+}
+
+/// A declarative set of folder shares applied through `WSLPluginAPI_MountFolder`.
+///
+/// The plan remembers what it mounted in each session so that a mount shared by several
+/// distributions is only created once; call [`MountPlan::on_vm_stopping`] to forget a session.
+pub struct MountPlan {
+    mounts: Vec<MountSpec>,
+    resolver: Box<dyn PathVariableResolver + Send + Sync>,
+    /// `(name, normalized Linux path)` of the mounts created in each session.
+    sessions: Mutex<HashMap<WSLSessionId, Vec<(String, String)>>>,
+}
+
+impl fmt::Debug for MountPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MountPlan")
+            .field("mounts", &self.mounts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl MountPlan {
+    /// Validates `mounts` and builds a plan resolving variables for the session's user.
+    pub fn new(mounts: Vec<MountSpec>) -> Result<Self, MountPlanError> {
+        for (i, mount) in mounts.iter().enumerate() {
+            for (field, value) in [
+                ("name", &mount.name),
+                ("Windows path", &mount.windows_path),
+                ("Linux path", &mount.linux_path),
+            ] {
+                if value.trim().is_empty() {
+                    return Err(MountPlanError::EmptyField {
+                        name: mount.name.clone(),
+                        field,
+                    });
+                }
+            }
+            if !mount.linux_path.starts_with('/') && !mount.linux_path.starts_with('%') {
+                return Err(MountPlanError::RelativeLinuxPath {
+                    name: mount.name.clone(),
+                });
+            }
+            for previous in &mounts[..i] {
+                if previous.name.eq_ignore_ascii_case(&mount.name) {
+                    return Err(MountPlanError::DuplicateName(mount.name.clone()));
+                }
+                if normalize_linux_path(&previous.linux_path)
+                    == normalize_linux_path(&mount.linux_path)
+                {
+                    return Err(MountPlanError::DuplicateLinuxPath {
+                        first: previous.name.clone(),
+                        second: mount.name.clone(),
+                    });
+                }
+            }
+        }
+        Ok(Self {
+            mounts,
+            resolver: default_resolver(),
+            sessions: Mutex::default(),
+        })
+    }
+
+    /// Replaces the resolver used to expand `%NAME%` variables.
+    pub fn with_resolver<R: PathVariableResolver + Send + Sync + 'static>(
+        mut self,
+        resolver: R,
+    ) -> Self {
+        self.resolver = Box::new(resolver);
+        self
+    }
+
+    pub fn mounts(&self) -> &[MountSpec] {
+        &self.mounts
+    }
+
+    /// Applies the mounts that do not depend on a distribution. Call it from `OnVMStarted`.
+    ///
+    /// # Safety
+    ///
+    /// `api` must be the table received at the plugin entry point and `session` the one passed
+    /// to the hook.
+    pub unsafe fn on_vm_started(
+        &self,
+        api: &WSLPluginAPIV1,
+        session: &WSLSessionInformation,
+    ) -> MountReport {
+        self.apply(api, session, |mount| mount.distributions.is_none())
+    }
+
+    /// Applies the mounts whose rule matches `distribution`. Call it from
+    /// `OnDistributionStarted`.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`MountPlan::on_vm_started`], and `distribution` must satisfy the
+    /// requirements of [`crate::DistributionInfo::from_distribution_information`].
+    pub unsafe fn on_distribution_started(
+        &self,
+        api: &WSLPluginAPIV1,
+        session: &WSLSessionInformation,
+        distribution: &WSLDistributionInformation,
+    ) -> MountReport {
+        let distribution = crate::DistributionInfo::from_distribution_information(distribution);
+        self.apply(api, session, |mount| {
+            mount
+                .distributions
+                .as_ref()
+                .is_some_and(|rule| rule.matches(&distribution))
+        })
+    }
+
+    /// Forgets what was mounted in the session. Call it from `OnVMStopping`.
+    pub fn on_vm_stopping(&self, session: &WSLSessionInformation) {
+        self.lock_sessions().remove(&session.SessionId);
+    }
+
+    fn lock_sessions(
+        &self,
+    ) -> std::sync::MutexGuard<'_, HashMap<WSLSessionId, Vec<(String, String)>>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    unsafe fn apply<F: Fn(&MountSpec) -> bool>(
+        &self,
+        api: &WSLPluginAPIV1,
+        session: &WSLSessionInformation,
+        filter: F,
+    ) -> MountReport {
+        let mut report = MountReport::default();
+        let selected: Vec<&MountSpec> = self.mounts.iter().filter(|m| filter(m)).collect();
+        if selected.is_empty() {
+            return report;
+        }
+        let user = self.resolver.resolve(session, "USERNAME");
+        for mount in selected {
+            let mut outcome = MountOutcome {
+                name: mount.name.clone(),
+                windows_path: None,
+                linux_path: None,
+                skipped: None,
+                result: S_OK,
+            };
+            if !mount.users.is_empty() {
+                outcome.skipped = match user.as_deref() {
+                    Some(user) if mount.users.iter().any(|p| p.matches(user)) => None,
+                    Some(user) => Some(MountSkip::OtherUser(user.to_owned())),
+                    None => Some(MountSkip::UnknownUser),
+                };
+                if outcome.skipped.is_some() {
+                    outcome.result = S_FALSE;
+                    report.outcomes.push(outcome);
+                    continue;
+                }
+            }
+            let paths = expand_path(&mount.windows_path, session, self.resolver.as_ref()).and_then(
+                |windows_path| {
+                    let linux_path =
+                        expand_path(&mount.linux_path, session, self.resolver.as_ref())?;
+                    Ok((windows_path, linux_path))
+                },
+            );
+            let (windows_path, linux_path) = match paths {
+                Ok(paths) => paths,
+                Err(_) => {
+                    outcome.result = ERROR_ENVVAR_NOT_FOUND.to_hresult();
+                    report.outcomes.push(outcome);
+                    continue;
+                }
+            };
+            outcome.windows_path = Some(windows_path.clone());
+            outcome.linux_path = Some(linux_path.clone());
+            let linux_key = normalize_linux_path(&linux_path);
+            let same_name = |name: &String| name.eq_ignore_ascii_case(&mount.name);
+            // The mount is reserved under the lock and the reservation dropped if it fails, so
+            // the lock is not held across `MountFolder` and concurrent hooks do not mount twice;
+            // a hook finding the reservation of another one reports `S_FALSE` right away.
+            let known = {
+                let mut sessions = self.lock_sessions();
+                let mounted = sessions.entry(session.SessionId).or_default();
+                if mounted
+                    .iter()
+                    .any(|(name, path)| same_name(name) && *path == linux_key)
+                {
+                    Some(S_FALSE)
+                } else if mounted
+                    .iter()
+                    .any(|(name, path)| same_name(name) || *path == linux_key)
+                {
+                    Some(ERROR_ALREADY_EXISTS.to_hresult())
+                } else {
+                    mounted.push((mount.name.clone(), linux_key.clone()));
+                    None
+                }
+            };
+            outcome.result = match known {
+                Some(result) => result,
+                None => {
+                    let result = mount_folder(
+                        api,
+                        session.SessionId,
+                        &windows_path,
+                        &linux_path,
+                        mount.read_only,
+                        &mount.name,
+                    );
+                    if result.is_err() {
+                        if let Some(mounted) = self.lock_sessions().get_mut(&session.SessionId) {
+                            mounted.retain(|(name, path)| !(same_name(name) && *path == linux_key));
+                        }
+                    }
+                    result
+                }
+            };
+            report.outcomes.push(outcome);
+        }
+        report
+    }
+}
+
+fn default_resolver() -> Box<dyn PathVariableResolver + Send + Sync> {
+    #[cfg(windows)]
+    {
+        Box::new(SessionUserEnvironmentResolver)
+    }
+    #[cfg(not(windows))]
+    {
+        Box::new(ProcessEnvironmentResolver)
+    }
+}
+
+fn normalize_linux_path(path: &str) -> String {
+    let trimmed = path.trim_end_matches('/');
+    if trimmed.is_empty() {
+        "/".into()
+    } else {
+        trimmed.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::read_wide_string;
+    use crate::test_support::api_table;
+    use crate::windows::core::{BOOL, PCWSTR};
+    use crate::windows::Win32::Foundation::HANDLE;
+    use crate::windows::Win32::Security::PSID;
+    use std::cell::{Cell, RefCell};
+
+    type MountCall = (WSLSessionId, String, String, bool, String);
+
+    thread_local! {
+        static CALLS: RefCell<Vec<MountCall>> = const { RefCell::new(Vec::new()) };
+    }
+
+    unsafe extern "C" fn recording_mount_folder(
+        session: WSLSessionId,
+        windows_path: PCWSTR,
+        linux_path: PCWSTR,
+        read_only: BOOL,
+        name: PCWSTR,
+    ) -> HRESULT {
+        let name = read_wide_string(name).unwrap();
+        CALLS.with_borrow_mut(|calls| {
+            calls.push((
+                session,
+                read_wide_string(windows_path).unwrap(),
+                read_wide_string(linux_path).unwrap(),
+                read_only.as_bool(),
+                name.clone(),
+            ))
+        });
+        if name == "broken" {
+            crate::windows::Win32::Foundation::E_ACCESSDENIED
+        } else {
+            S_OK
+        }
+    }
+
+    fn api() -> WSLPluginAPIV1 {
+        WSLPluginAPIV1 {
+            MountFolder: Some(recording_mount_folder),
+            ..api_table(2, 4, 4)
+        }
+    }
+
+    fn session(id: WSLSessionId) -> WSLSessionInformation {
+        WSLSessionInformation {
+            SessionId: id,
+            UserToken: HANDLE::default(),
+            UserSid: PSID::default(),
+        }
+    }
+
+    fn resolver(_: &WSLSessionInformation, name: &str) -> Option<String> {
+        match name {
+            "USERPROFILE" => Some(r"C:\Users\alice".into()),
+            "USERNAME" => Some("alice".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_expand_path() {
+        let session = session(1);
+        assert_eq!(
+            expand_path(r"%USERPROFILE%\src", &session, &resolver).unwrap(),
+            r"C:\Users\alice\src"
+        );
+        assert_eq!(expand_path("100%%", &session, &resolver).unwrap(), "100%");
+        assert_eq!(
+            expand_path("50% off", &session, &resolver).unwrap(),
+            "50% off"
+        );
+        assert_eq!(
+            expand_path("%MISSING%", &session, &resolver),
+            Err(UnresolvedVariableError("MISSING".into()))
+        );
+    }
+
+    #[test]
+    fn test_plan_validation() {
+        assert_eq!(
+            MountPlan::new(vec![
+                MountSpec::new("src", r"C:\src", "/mnt/src"),
+                MountSpec::new("SRC", r"C:\other", "/mnt/other"),
+            ])
+            .unwrap_err(),
+            MountPlanError::DuplicateName("SRC".into())
+        );
+        assert!(matches!(
+            MountPlan::new(vec![
+                MountSpec::new("a", r"C:\a", "/mnt/x/"),
+                MountSpec::new("b", r"C:\b", "/mnt/x"),
+            ]),
+            Err(MountPlanError::DuplicateLinuxPath { .. })
+        ));
+        assert!(matches!(
+            MountPlan::new(vec![MountSpec::new("a", r"C:\a", "mnt/a")]),
+            Err(MountPlanError::RelativeLinuxPath { .. })
+        ));
+    }
+
+    #[test]
+    fn test_apply_on_vm_started() {
+        let plan = MountPlan::new(vec![
+            MountSpec::new("profile", r"%USERPROFILE%", "/mnt/profile").read_only(true),
+            MountSpec::new("broken", r"C:\broken", "/mnt/broken"),
+            MountSpec::new("bob", r"C:\bob", "/mnt/bob").for_user("bob"),
+            MountSpec::new("unknown", r"%NOPE%", "/mnt/nope"),
+            MountSpec::new("later", r"C:\later", "/mnt/later")
+                .for_distributions(DistributionRule::All),
+        ])
+        .unwrap()
+        .with_resolver(resolver);
+        let report = unsafe { plan.on_vm_started(&api(), &session(7)) };
+        let results: Vec<_> = report
+            .outcomes
+            .iter()
+            .map(|o| (o.name.as_str(), o.result))
+            .collect();
+        assert_eq!(
+            results,
+            [
+                ("profile", S_OK),
+                ("broken", crate::windows::Win32::Foundation::E_ACCESSDENIED),
+                ("bob", S_FALSE),
+                ("unknown", ERROR_ENVVAR_NOT_FOUND.to_hresult()),
+            ]
+        );
+        assert_eq!(
+            report.outcomes[2].skipped,
+            Some(MountSkip::OtherUser("alice".into()))
+        );
+        assert_eq!(report.outcomes[0].skipped, None);
+        assert_eq!(
+            report.result(),
+            crate::windows::Win32::Foundation::E_ACCESSDENIED
+        );
+        let calls = CALLS.take();
+        assert_eq!(
+            calls[0],
+            (
+                7,
+                r"C:\Users\alice".into(),
+                "/mnt/profile".into(),
+                true,
+                "profile".into()
+            )
+        );
+    }
+
+    #[test]
+    fn test_user_mounts_are_skipped_without_user() {
+        let plan = MountPlan::new(vec![
+            MountSpec::new("bob", r"C:\bob", "/mnt/bob").for_user("bob"),
+            MountSpec::new("shared", r"C:\shared", "/mnt/shared"),
+        ])
+        .unwrap()
+        .with_resolver(|_: &WSLSessionInformation, _: &str| None);
+        let report = unsafe { plan.on_vm_started(&api(), &session(8)) };
+        assert_eq!(report.outcomes[0].skipped, Some(MountSkip::UnknownUser));
+        assert_eq!(report.outcomes[0].result, S_FALSE);
+        assert_eq!(report.outcomes[1].skipped, None);
+        assert!(report.is_success());
+        let calls = CALLS.take();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].4, "shared");
+    }
+
+    #[cfg(wsl_plugin_api_ge_2_4_4)]
+    #[test]
+    fn test_distribution_mounts_are_applied_once_per_session() {
+        use crate::strings::to_wide_string;
+        use crate::windows::core::GUID;
+
+        let plan = MountPlan::new(vec![MountSpec::new("tools", r"C:\tools", "/opt/tools")
+            .for_distributions("flavor = ubuntu".parse().unwrap())])
+        .unwrap()
+        .with_resolver(resolver);
+        let name = to_wide_string("Ubuntu");
+        let flavor = to_wide_string("ubuntu");
+        let distribution = WSLDistributionInformation {
+            Id: GUID::zeroed(),
+            Name: PCWSTR(name.as_ptr()),
+            PidNamespace: 0,
+            PackageFamilyName: PCWSTR::null(),
+            InitPid: 1,
+            Flavor: PCWSTR(flavor.as_ptr()),
+            Version: PCWSTR::null(),
+        };
+        let api = api();
+        let session = session(3);
+        let first = unsafe { plan.on_distribution_started(&api, &session, &distribution) };
+        let second = unsafe { plan.on_distribution_started(&api, &session, &distribution) };
+        assert_eq!(first.outcomes[0].result, S_OK);
+        assert_eq!(second.outcomes[0].result, S_FALSE);
+        assert!(second.is_success());
+        plan.on_vm_stopping(&session);
+        let third = unsafe { plan.on_distribution_started(&api, &session, &distribution) };
+        assert_eq!(third.outcomes[0].result, S_OK);
+        assert_eq!(CALLS.take().len(), 2);
+    }
+
+    thread_local! {
+        static REENTERED_PLAN: Cell<*const MountPlan> = const { Cell::new(std::ptr::null()) };
+    }
+
+    unsafe extern "C" fn reentrant_mount_folder(
+        session_id: WSLSessionId,
+        _windows_path: PCWSTR,
+        _linux_path: PCWSTR,
+        _read_only: BOOL,
+        _name: PCWSTR,
+    ) -> HRESULT {
+        // Uses the plan from inside the call, as a hook running meanwhile would.
+        (*REENTERED_PLAN.get()).on_vm_stopping(&session(session_id));
+        S_OK
+    }
+
+    #[test]
+    fn test_lock_is_released_during_mount_folder() {
+        let plan = MountPlan::new(vec![MountSpec::new("tools", r"C:\tools", "/opt/tools")])
+            .unwrap()
+            .with_resolver(resolver);
+        REENTERED_PLAN.set(&plan);
+        let api = WSLPluginAPIV1 {
+            MountFolder: Some(reentrant_mount_folder),
+            ..api()
+        };
+        let report = unsafe { plan.on_vm_started(&api, &session(5)) };
+        assert_eq!(report.result(), S_OK);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_plan_from_config_file() {
+        #[derive(serde::Deserialize)]
+        struct Config {
+            mounts: Vec<MountSpec>,
+        }
+        let config: Config = toml::from_str(
+            r#"
+            [[mounts]]
+            name = "profile"
+            windows_path = '%USERPROFILE%'
+            linux_path = "/mnt/profile"
+            read_only = true
+            users = ["alice"]
+
+            [[mounts]]
+            name = "tools"
+            windows_path = 'C:\tools'
+            linux_path = "/opt/tools"
+            distributions = "flavor = ubuntu"
+            "#,
+        )
+        .unwrap();
+        let plan = MountPlan::new(config.mounts).unwrap();
+        assert!(plan.mounts()[0].read_only);
+        assert_eq!(
+            plan.mounts()[1].distributions,
+            Some(DistributionRule::flavor(crate::DistributionFlavor::Ubuntu))
+        );
+    }
+}
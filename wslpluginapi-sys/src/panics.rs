@@ -0,0 +1,10 @@
+use std::any::Any;
+
+/// Returns the message of a panic payload caught with `catch_unwind`.
+pub(crate) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
@@ -0,0 +1,386 @@
+use crate::windows::core::HRESULT;
+use crate::windows::Win32::Foundation::E_ACCESSDENIED;
+use crate::{ApiCall, GlobPattern, Interceptor};
+use std::fmt;
+
+/// Result returned to the plugin for a call rejected by a [`Policy`].
+pub const POLICY_VIOLATION: HRESULT = E_ACCESSDENIED;
+
+/// Binaries that may be started with `ExecuteBinary` or `ExecuteBinaryInDistribution`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExecutableRule {
+    /// Pattern the path of the binary must match, case included; see
+    /// [`GlobPattern::matches_path`].
+    pub path: GlobPattern,
+    /// Patterns each argument after `argv[0]` must match one of, case included.
+    ///
+    /// Any argument is allowed when `None`, and none when empty.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arguments: Option<Vec<GlobPattern>>,
+}
+
+impl ExecutableRule {
+    pub fn new<P: Into<GlobPattern>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            arguments: None,
+        }
+    }
+
+    /// Restricts the arguments to the ones matching `patterns`.
+    pub fn arguments<I, P>(mut self, patterns: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<GlobPattern>,
+    {
+        self.arguments = Some(patterns.into_iter().map(Into::into).collect());
+        self
+    }
+
+    fn allows(&self, path: &str, arguments: &[String]) -> bool {
+        self.path.matches_path(path)
+            && match &self.arguments {
+                None => true,
+                Some(patterns) => arguments.iter().skip(1).all(|argument| {
+                    patterns
+                        .iter()
+                        .any(|pattern| pattern.matches_case_sensitive(argument))
+                }),
+            }
+    }
+}
+
+/// Windows folders that may be mounted with `MountFolder`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MountRule {
+    /// Folder the Windows path must be, or be under; compared without case.
+    pub windows_root: String,
+    /// Patterns the Linux path must match one of, case included and as in
+    /// [`GlobPattern::matches_path`]; any path when empty.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub linux_targets: Vec<GlobPattern>,
+    /// Whether the mount has to be read-only.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub read_only: bool,
+}
+
+impl MountRule {
+    pub fn new<S: Into<String>>(windows_root: S) -> Self {
+        Self {
+            windows_root: windows_root.into(),
+            linux_targets: Vec::new(),
+            read_only: false,
+        }
+    }
+
+    pub fn linux_target<P: Into<GlobPattern>>(mut self, pattern: P) -> Self {
+        self.linux_targets.push(pattern.into());
+        self
+    }
+
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    fn allows(&self, windows_path: &[String], linux_path: &str, read_only: bool) -> bool {
+        let Some(root) = windows_components(&self.windows_root) else {
+            return false;
+        };
+        windows_path.len() >= root.len()
+            && root.iter().zip(windows_path).all(|(a, b)| a == b)
+            && (self.linux_targets.is_empty()
+                || self
+                    .linux_targets
+                    .iter()
+                    .any(|pattern| pattern.matches_path(linux_path)))
+            && (read_only || !self.read_only)
+    }
+}
+
+/// Splits a Windows path into lowercase components, or `None` if it is relative or contains
+/// `.` or `..` components.
+fn windows_components(path: &str) -> Option<Vec<String>> {
+    let path = path.strip_prefix(r"\\?\").unwrap_or(path);
+    let mut components = path
+        .split(['\\', '/'])
+        .filter(|component| !component.is_empty());
+    let first = components.next()?;
+    if !((first.len() == 2 && first.ends_with(':')) || path.starts_with(r"\\")) {
+        return None;
+    }
+    std::iter::once(first)
+        .chain(components)
+        .map(|component| match component {
+            "." | ".." => None,
+            component => Some(component.to_lowercase()),
+        })
+        .collect()
+}
+
+/// Returns whether `path` is absolute and contains no empty, `.` or `..` component, which
+/// wildcards could otherwise be used to escape from.
+fn is_normalized_linux_path(path: &str) -> bool {
+    path.strip_prefix('/').is_some_and(|relative| {
+        relative.is_empty()
+            || relative
+                .split('/')
+                .all(|component| !matches!(component, "" | "." | ".."))
+    })
+}
+
+/// A call rejected by a [`Policy`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PolicyViolation {
+    pub function: &'static str,
+    pub reason: String,
+}
+
+impl fmt::Display for PolicyViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} rejected by policy: {}", self.function, self.reason)
+    }
+}
+
+impl std::error::Error for PolicyViolation {}
+
+/// Allow-lists for the binaries a plugin may start and the folders it may mount.
+///
+/// Anything not allowed is denied; `PluginError` is always allowed. Installed as an
+/// [`Interceptor`], the policy answers violations with [`POLICY_VIOLATION`] and logs them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Policy {
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub executables: Vec<ExecutableRule>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub mounts: Vec<MountRule>,
+}
+
+impl Policy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow_executable(mut self, rule: ExecutableRule) -> Self {
+        self.executables.push(rule);
+        self
+    }
+
+    pub fn allow_mount(mut self, rule: MountRule) -> Self {
+        self.mounts.push(rule);
+        self
+    }
+
+    /// Checks a call against the policy.
+    pub fn check(&self, call: &ApiCall) -> Result<(), PolicyViolation> {
+        let violation = |reason: String| {
+            Err(PolicyViolation {
+                function: call.function(),
+                reason,
+            })
+        };
+        match call {
+            ApiCall::MountFolder {
+                windows_path,
+                linux_path,
+                read_only,
+                ..
+            } => {
+                let Some(components) = windows_components(windows_path) else {
+                    return violation(format!(
+                        "{windows_path:?} is not a normalized absolute path"
+                    ));
+                };
+                if !is_normalized_linux_path(linux_path) {
+                    return violation(format!("{linux_path:?} is not a normalized absolute path"));
+                }
+                if self
+                    .mounts
+                    .iter()
+                    .any(|rule| rule.allows(&components, linux_path, *read_only))
+                {
+                    Ok(())
+                } else {
+                    violation(format!(
+                        "mounting {windows_path:?} on {linux_path:?} (read_only={read_only}) is not allowed"
+                    ))
+                }
+            }
+            ApiCall::ExecuteBinary {
+                path, arguments, ..
+            }
+            | ApiCall::ExecuteBinaryInDistribution {
+                path, arguments, ..
+            } => {
+                if !is_normalized_linux_path(path) {
+                    return violation(format!("{path:?} is not a normalized absolute path"));
+                }
+                if self
+                    .executables
+                    .iter()
+                    .any(|rule| rule.allows(path, arguments))
+                {
+                    Ok(())
+                } else {
+                    violation(format!(
+                        "running {path:?} with {arguments:?} is not allowed"
+                    ))
+                }
+            }
+            ApiCall::PluginError { .. } => Ok(()),
+        }
+    }
+}
+
+impl Interceptor for Policy {
+    fn before(&self, call: &ApiCall) -> Option<HRESULT> {
+        let violation = self.check(call).err()?;
+        log::warn!("{call}: {violation}");
+        Some(POLICY_VIOLATION)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::windows::core::GUID;
+
+    fn execute(path: &str, arguments: &[&str]) -> ApiCall {
+        ApiCall::ExecuteBinaryInDistribution {
+            session: 1,
+            distribution: GUID::zeroed(),
+            path: path.into(),
+            arguments: arguments
+                .iter()
+                .map(|argument| argument.to_string())
+                .collect(),
+        }
+    }
+
+    fn mount(windows_path: &str, linux_path: &str, read_only: bool) -> ApiCall {
+        ApiCall::MountFolder {
+            session: 1,
+            windows_path: windows_path.into(),
+            linux_path: linux_path.into(),
+            read_only,
+            name: "share".into(),
+        }
+    }
+
+    #[test]
+    fn test_executables() {
+        let policy = Policy::new()
+            .allow_executable(ExecutableRule::new("/usr/bin/systemctl").arguments([
+                "is-active",
+                "--quiet",
+                "*.service",
+            ]))
+            .allow_executable(ExecutableRule::new("/opt/plugin/bin/*"));
+        assert!(policy
+            .check(&execute(
+                "/usr/bin/systemctl",
+                &["systemctl", "is-active", "docker.service"]
+            ))
+            .is_ok());
+        assert!(policy
+            .check(&execute(
+                "/usr/bin/systemctl",
+                &["systemctl", "stop", "docker.service"]
+            ))
+            .is_err());
+        assert!(policy
+            .check(&execute("/opt/plugin/bin/agent", &["agent", "--anything"]))
+            .is_ok());
+        // Case matters for Linux paths, `*` stays within a directory and `..` is rejected.
+        assert!(policy
+            .check(&execute("/OPT/plugin/bin/agent", &[]))
+            .is_err());
+        assert!(policy
+            .check(&execute("/opt/plugin/bin/sub/agent", &[]))
+            .is_err());
+        assert!(policy
+            .check(&execute("/opt/plugin/bin/../../../bin/sh", &[]))
+            .is_err());
+        assert!(policy.check(&execute("/bin/sh", &["sh"])).is_err());
+    }
+
+    #[test]
+    fn test_mounts() {
+        let policy = Policy::new()
+            .allow_mount(MountRule::new(r"C:\Shared").linux_target("/mnt/shared/*"))
+            .allow_mount(MountRule::new(r"D:\").read_only());
+        assert!(policy
+            .check(&mount(r"c:\shared\tools", "/mnt/shared/tools", false))
+            .is_ok());
+        assert!(policy
+            .check(&mount(r"C:\SharedData", "/mnt/shared/data", false))
+            .is_err());
+        assert!(policy
+            .check(&mount(r"C:\Shared\..\Users", "/mnt/shared/users", false))
+            .is_err());
+        assert!(policy
+            .check(&mount(r"C:\Shared", "/mnt/other", false))
+            .is_err());
+        assert!(policy
+            .check(&mount(r"C:\Shared", "/mnt/shared/nested/dir", false))
+            .is_err());
+        let nested =
+            Policy::new().allow_mount(MountRule::new(r"C:\Shared").linux_target("/mnt/shared/**"));
+        assert!(nested
+            .check(&mount(r"C:\Shared", "/mnt/shared/nested/dir", false))
+            .is_ok());
+        assert!(policy.check(&mount(r"D:\Data", "/mnt/d", true)).is_ok());
+        assert!(policy.check(&mount(r"D:\Data", "/mnt/d", false)).is_err());
+        assert!(Policy::new()
+            .check(&ApiCall::PluginError {
+                message: "failed".into()
+            })
+            .is_ok());
+    }
+
+    #[test]
+    fn test_interceptor() {
+        let policy = Policy::new().allow_executable(ExecutableRule::new("/usr/bin/true"));
+        assert_eq!(policy.before(&execute("/usr/bin/true", &["true"])), None);
+        assert_eq!(
+            policy.before(&execute("/usr/bin/false", &["false"])),
+            Some(POLICY_VIOLATION)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_policy_from_config() {
+        let policy: Policy = toml::from_str(
+            r#"
+            [[executables]]
+            path = "/usr/bin/systemctl"
+            arguments = ["is-active", "*.service"]
+
+            [[mounts]]
+            windows_root = 'C:\Shared'
+            linux_targets = ["/mnt/shared"]
+            read_only = true
+            "#,
+        )
+        .unwrap();
+        assert_eq!(
+            policy,
+            Policy::new()
+                .allow_executable(
+                    ExecutableRule::new("/usr/bin/systemctl").arguments(["is-active", "*.service"])
+                )
+                .allow_mount(
+                    MountRule::new(r"C:\Shared")
+                        .linux_target("/mnt/shared")
+                        .read_only()
+                )
+        );
+        assert!(policy
+            .check(&mount(r"C:\Shared", "/mnt/shared", true))
+            .is_ok());
+    }
+}
@@ -0,0 +1,701 @@
+use crate::windows::core::HRESULT;
+use crate::windows::Win32::Foundation::{ERROR_TIMEOUT, E_FAIL, S_OK};
+use crate::{
+    execute_binary, plugin_error, DistributionRule, ProcessStream, WSLPluginAPIV1,
+    WSLSessionInformation,
+};
+#[cfg(wsl_plugin_api_ge_2_1_2)]
+use crate::{execute_binary_in_distribution, DistributionInfo, WSLDistributionInformation};
+use log::{debug, error, warn};
+use std::fmt;
+use std::io::{self, Read};
+use std::net::Shutdown;
+use std::time::{Duration, Instant};
+
+const EXIT_STATUS_MARKER: &str = "__WSLPLUGINAPI_EXIT_STATUS__";
+/// Runs `"$@"` with stderr merged into stdout and kills it after `$1` seconds, then reports its
+/// exit status, or `timeout` if it was killed, on a last line. The watchdog only exits
+/// successfully when it killed the command; once it fires it ignores the `TERM` meant to cancel
+/// it.
+const WRAPPER_SCRIPT: &str = "t=$1; shift; \
+    \"$@\" 2>&1 </dev/null & pid=$!; \
+    (sleep \"$t\"; trap '' TERM; kill -KILL \"$pid\") >/dev/null 2>&1 </dev/null & watchdog=$!; \
+    wait \"$pid\" 2>/dev/null; status=$?; \
+    kill \"$watchdog\" 2>/dev/null; \
+    if wait \"$watchdog\" 2>/dev/null; \
+    then printf '\\n%s timeout\\n' __WSLPLUGINAPI_EXIT_STATUS__; \
+    else printf '\\n%s %d\\n' __WSLPLUGINAPI_EXIT_STATUS__ \"$status\"; fi";
+/// How long the plugin waits past the timeout for the distribution to report the kill.
+const KILL_GRACE: Duration = Duration::from_secs(5);
+/// Only the end of the output is kept for the logs.
+const MAX_OUTPUT_LEN: usize = 64 * 1024;
+
+/// What to do when a startup command fails.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum FailurePolicy {
+    /// Carry on silently.
+    Ignore,
+    /// Log a warning and carry on.
+    #[default]
+    Warn,
+    /// Report the failure with `PluginError` and make the hook fail, which aborts the start of
+    /// the VM or distribution. The remaining commands are not run.
+    Fail,
+}
+
+/// A command started by the plugin when the VM or a distribution starts.
+///
+/// Commands without `distributions` run in the root namespace of the VM from `OnVMStarted`,
+/// the others in every matching distribution from `OnDistributionStarted`. Commands run one at
+/// a time, by ascending `order` then declaration order, and are killed when they run longer than
+/// `timeout`, [`StartupCommand::DEFAULT_TIMEOUT`] unless set. In configuration files `timeout`
+/// is expressed in seconds.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StartupCommand {
+    pub name: String,
+    pub path: String,
+    /// Arguments passed after `argv[0]`, which is always `path`.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub arguments: Vec<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub order: i32,
+    #[cfg_attr(
+        feature = "serde",
+        serde(default = "StartupCommand::default_timeout", with = "seconds")
+    )]
+    pub timeout: Duration,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub failure_policy: FailurePolicy,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub distributions: Option<DistributionRule>,
+}
+
+impl StartupCommand {
+    /// Timeout of the commands that do not set one. The hooks running the commands hold up the
+    /// start of the VM or distribution, so no command may run for ever.
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+    pub fn new<N: Into<String>, P: Into<String>>(name: N, path: P) -> Self {
+        Self {
+            name: name.into(),
+            path: path.into(),
+            arguments: Vec::new(),
+            order: 0,
+            timeout: Self::DEFAULT_TIMEOUT,
+            failure_policy: FailurePolicy::default(),
+            distributions: None,
+        }
+    }
+
+    pub fn arg<S: Into<String>>(mut self, argument: S) -> Self {
+        self.arguments.push(argument.into());
+        self
+    }
+
+    pub fn order(mut self, order: i32) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    pub fn failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
+    }
+
+    /// Runs the command in the distributions matching `rule` instead of the root namespace.
+    pub fn for_distributions(mut self, rule: DistributionRule) -> Self {
+        self.distributions = Some(rule);
+        self
+    }
+}
+
+#[cfg(feature = "serde")]
+impl StartupCommand {
+    fn default_timeout() -> Duration {
+        Self::DEFAULT_TIMEOUT
+    }
+}
+
+#[cfg(feature = "serde")]
+mod seconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(value: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(value.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Duration::try_from_secs_f64(f64::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+/// How a startup command ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CommandStatus {
+    /// The command exited with the given status.
+    Exited(i32),
+    /// WSL could not start the command.
+    LaunchFailed(HRESULT),
+    /// The command did not finish before its timeout and was killed.
+    TimedOut,
+    /// The connection to the command broke before it reported its exit status.
+    Disconnected,
+}
+
+impl CommandStatus {
+    pub fn is_success(&self) -> bool {
+        *self == Self::Exited(0)
+    }
+
+    /// The `HRESULT` reported to WSL when the command fails the start.
+    pub fn to_hresult(&self) -> HRESULT {
+        match self {
+            Self::Exited(0) => S_OK,
+            Self::LaunchFailed(result) => *result,
+            Self::TimedOut => ERROR_TIMEOUT.to_hresult(),
+            Self::Exited(_) | Self::Disconnected => E_FAIL,
+        }
+    }
+}
+
+impl fmt::Display for CommandStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited(status) => write!(f, "exited with status {status}"),
+            Self::LaunchFailed(result) => write!(f, "could not be started ({result})"),
+            Self::TimedOut => f.write_str("timed out"),
+            Self::Disconnected => f.write_str("disconnected before completion"),
+        }
+    }
+}
+
+/// Result of a single startup command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutcome {
+    pub name: String,
+    pub status: CommandStatus,
+    /// Standard output and error of the command, truncated to its last 64 KiB.
+    pub output: String,
+    pub elapsed: Duration,
+}
+
+/// Results of the commands run in a hook.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StartupReport {
+    pub outcomes: Vec<CommandOutcome>,
+    /// The value the hook should return: `S_OK` unless a command with
+    /// [`FailurePolicy::Fail`] failed.
+    pub result: HRESULT,
+}
+
+/// Runs [`StartupCommand`]s through `ExecuteBinary` and `ExecuteBinaryInDistribution`.
+#[derive(Debug, Clone, Default)]
+pub struct StartupCommandRunner {
+    commands: Vec<StartupCommand>,
+}
+
+impl StartupCommandRunner {
+    pub fn new(mut commands: Vec<StartupCommand>) -> Self {
+        commands.sort_by_key(|command| command.order);
+        Self { commands }
+    }
+
+    /// The commands, in execution order.
+    pub fn commands(&self) -> &[StartupCommand] {
+        &self.commands
+    }
+
+    /// Runs the root namespace commands. Call it from `OnVMStarted` and return
+    /// [`StartupReport::result`].
+    ///
+    /// # Safety
+    ///
+    /// `api` must be the table received at the plugin entry point and `session` the one passed
+    /// to the hook.
+    pub unsafe fn on_vm_started(
+        &self,
+        api: &WSLPluginAPIV1,
+        session: &WSLSessionInformation,
+    ) -> StartupReport {
+        let commands = self.commands.iter().filter(|c| c.distributions.is_none());
+        self.run(api, commands, |command| {
+            let argv = wrapped_argv(command);
+            let argv: Vec<&str> = argv.iter().map(String::as_str).collect();
+            execute_binary(api, session.SessionId, argv[0], &argv)
+        })
+    }
+
+    /// Runs the commands matching `distribution` inside it. Call it from
+    /// `OnDistributionStarted` and return [`StartupReport::result`].
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`StartupCommandRunner::on_vm_started`], and `distribution` must
+    /// satisfy the requirements of [`DistributionInfo::from_distribution_information`].
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    pub unsafe fn on_distribution_started(
+        &self,
+        api: &WSLPluginAPIV1,
+        session: &WSLSessionInformation,
+        distribution: &WSLDistributionInformation,
+    ) -> StartupReport {
+        let info = DistributionInfo::from_distribution_information(distribution);
+        let commands = self.commands.iter().filter(|command| {
+            command
+                .distributions
+                .as_ref()
+                .is_some_and(|rule| rule.matches(&info))
+        });
+        self.run(api, commands, |command| {
+            let argv = wrapped_argv(command);
+            let argv: Vec<&str> = argv.iter().map(String::as_str).collect();
+            execute_binary_in_distribution(api, session.SessionId, &info.id, argv[0], &argv)
+        })
+    }
+
+    unsafe fn run<'a, I, F>(
+        &self,
+        api: &WSLPluginAPIV1,
+        commands: I,
+        mut launch: F,
+    ) -> StartupReport
+    where
+        I: Iterator<Item = &'a StartupCommand>,
+        F: FnMut(&StartupCommand) -> crate::windows::core::Result<ProcessStream>,
+    {
+        let mut report = StartupReport {
+            outcomes: Vec::new(),
+            result: S_OK,
+        };
+        for command in commands {
+            let started = Instant::now();
+            let (status, output) = match launch(command) {
+                Ok(stream) => collect(stream, started + command.timeout + KILL_GRACE),
+                Err(error) => (CommandStatus::LaunchFailed(error.code()), String::new()),
+            };
+            let outcome = CommandOutcome {
+                name: command.name.clone(),
+                status,
+                output,
+                elapsed: started.elapsed(),
+            };
+            debug!(
+                "Startup command {:?} {} after {:?}: {}",
+                outcome.name, outcome.status, outcome.elapsed, outcome.output
+            );
+            let failed = !outcome.status.is_success();
+            let status = outcome.status.clone();
+            report.outcomes.push(outcome);
+            if !failed {
+                continue;
+            }
+            match command.failure_policy {
+                FailurePolicy::Ignore => {}
+                FailurePolicy::Warn => warn!("Startup command {:?} {}", command.name, status),
+                FailurePolicy::Fail => {
+                    error!("Startup command {:?} {}", command.name, status);
+                    let message = format!("Startup command '{}' {}", command.name, status);
+                    let _ = plugin_error(api, &message);
+                    report.result = status.to_hresult();
+                    break;
+                }
+            }
+        }
+        report
+    }
+}
+
+fn wrapped_argv(command: &StartupCommand) -> Vec<String> {
+    // `sleep` only takes whole seconds everywhere.
+    let timeout = command.timeout.as_secs() + u64::from(command.timeout.subsec_nanos() > 0);
+    [
+        "/bin/sh",
+        "-c",
+        WRAPPER_SCRIPT,
+        "sh",
+        &timeout.to_string(),
+        &command.path,
+    ]
+    .into_iter()
+    .map(str::to_owned)
+    .chain(command.arguments.iter().cloned())
+    .collect()
+}
+
+/// Reads the output of the wrapper script until it reports how the command ended. `deadline`
+/// only guards against a distribution that never reports it; the stream is then dropped.
+///
+/// Reading stops at the exit status line rather than at the end of the stream, which processes
+/// the command left running in the background keep open; what they write later is not
+/// collected.
+fn collect(mut stream: ProcessStream, deadline: Instant) -> (CommandStatus, String) {
+    // The command must not wait for input from the plugin.
+    let _ = stream.shutdown(Shutdown::Write);
+    let mut output = Vec::new();
+    let mut buffer = [0u8; 4096];
+    // Where the exit status line may start in the data not searched yet.
+    let mut unsearched = 0;
+    let status = loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() || stream.set_read_timeout(Some(remaining)).is_err() {
+            break CommandStatus::TimedOut;
+        }
+        match stream.read(&mut buffer) {
+            Ok(0) => break CommandStatus::Disconnected,
+            Ok(read) => {
+                output.extend_from_slice(&buffer[..read]);
+                if let Some((position, status)) = exit_status(&output, unsearched) {
+                    output.truncate(position);
+                    break status;
+                }
+                unsearched = output.len().saturating_sub(MAX_EXIT_STATUS_LINE_LEN);
+                if output.len() > 2 * MAX_OUTPUT_LEN {
+                    let excess = output.len() - MAX_OUTPUT_LEN;
+                    output.drain(..excess);
+                    unsearched -= excess;
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                break CommandStatus::TimedOut
+            }
+            Err(_) => break CommandStatus::Disconnected,
+        }
+    };
+    (status, tail(String::from_utf8_lossy(&output).into_owned()))
+}
+
+/// Longest exit status line of the wrapper script, `\n<marker> -2147483648\n`.
+const MAX_EXIT_STATUS_LINE_LEN: usize = EXIT_STATUS_MARKER.len() + 14;
+
+/// Finds the complete exit status line of the wrapper script starting at or after `from`,
+/// returning where it starts and the status it reports.
+fn exit_status(output: &[u8], from: usize) -> Option<(usize, CommandStatus)> {
+    let marker = format!("\n{EXIT_STATUS_MARKER} ");
+    let mut start = from;
+    while let Some(offset) = output[start..]
+        .windows(marker.len())
+        .position(|window| window == marker.as_bytes())
+    {
+        let position = start + offset;
+        let line = &output[position + marker.len()..];
+        // The line is not complete yet.
+        let end = line.iter().position(|byte| *byte == b'\n')?;
+        let status = match std::str::from_utf8(&line[..end]) {
+            Ok("timeout") => Some(CommandStatus::TimedOut),
+            Ok(code) => code.parse().ok().map(CommandStatus::Exited),
+            Err(_) => None,
+        };
+        if let Some(status) = status {
+            return Some((position, status));
+        }
+        start = position + 1;
+    }
+    None
+}
+
+fn tail(mut output: String) -> String {
+    if output.len() > MAX_OUTPUT_LEN {
+        let mut start = output.len() - MAX_OUTPUT_LEN;
+        while !output.is_char_boundary(start) {
+            start += 1;
+        }
+        output.drain(..start);
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::read_wide_string;
+    use crate::test_support::{api_table, argument_vector, socket_pair};
+    use crate::windows::core::{PCSTR, PCWSTR};
+    use crate::windows::Win32::Foundation::{E_ACCESSDENIED, HANDLE};
+    use crate::windows::Win32::Networking::WinSock::SOCKET;
+    use crate::windows::Win32::Security::PSID;
+    use crate::WSLSessionId;
+    use std::cell::RefCell;
+    use std::io::Write;
+
+    thread_local! {
+        static ERRORS: RefCell<Vec<String>> = const { RefCell::new(Vec::new()) };
+        static LAUNCHED: RefCell<Vec<Vec<String>>> = const { RefCell::new(Vec::new()) };
+    }
+
+    /// Pretends to be `/bin/sh -c WRAPPER_SCRIPT sh <timeout> <path> <args...>`.
+    unsafe fn fake_process(arguments: *mut PCSTR, socket: *mut SOCKET) -> HRESULT {
+        let argv = argument_vector(arguments);
+        assert_eq!(argv[..4], ["/bin/sh", "-c", WRAPPER_SCRIPT, "sh"]);
+        let timeout = Duration::from_secs(argv[4].parse().unwrap());
+        let command = argv[5..].to_vec();
+        LAUNCHED.with_borrow_mut(|launched| launched.push(command.clone()));
+        if command[0] == "/missing" {
+            return E_ACCESSDENIED;
+        }
+        let (host, mut guest) = socket_pair();
+        std::thread::spawn(move || match command[0].as_str() {
+            "/bin/echo" => {
+                let text = command[1..].join(" ");
+                write!(guest, "{text}\n\n{EXIT_STATUS_MARKER} 0\n").unwrap();
+            }
+            "/bin/false" => write!(guest, "\n{EXIT_STATUS_MARKER} 1\n").unwrap(),
+            // Leaves a process running in the background, which keeps the stream open.
+            "/usr/sbin/daemon" => {
+                write!(guest, "started\n\n{EXIT_STATUS_MARKER} 0\n").unwrap();
+                std::thread::sleep(timeout);
+                let _ = guest.write_all(b"late output\n");
+            }
+            _ => {
+                std::thread::sleep(timeout);
+                write!(guest, "\n{EXIT_STATUS_MARKER} timeout\n").unwrap();
+            }
+        });
+        *socket = host;
+        S_OK
+    }
+
+    unsafe extern "C" fn fake_execute_binary(
+        _session: WSLSessionId,
+        _path: PCSTR,
+        arguments: *mut PCSTR,
+        socket: *mut SOCKET,
+    ) -> HRESULT {
+        fake_process(arguments, socket)
+    }
+
+    #[cfg(wsl_plugin_api_ge_2_1_2)]
+    unsafe extern "C" fn fake_execute_binary_in_distribution(
+        _session: WSLSessionId,
+        _distribution: *const crate::windows::core::GUID,
+        _path: PCSTR,
+        arguments: *mut PCSTR,
+        socket: *mut SOCKET,
+    ) -> HRESULT {
+        fake_process(arguments, socket)
+    }
+
+    unsafe extern "C" fn fake_plugin_error(message: PCWSTR) -> HRESULT {
+        ERRORS.with_borrow_mut(|errors| errors.push(read_wide_string(message).unwrap()));
+        S_OK
+    }
+
+    fn api() -> WSLPluginAPIV1 {
+        WSLPluginAPIV1 {
+            ExecuteBinary: Some(fake_execute_binary),
+            PluginError: Some(fake_plugin_error),
+            #[cfg(wsl_plugin_api_ge_2_1_2)]
+            ExecuteBinaryInDistribution: Some(fake_execute_binary_in_distribution),
+            ..api_table(2, 4, 4)
+        }
+    }
+
+    fn session() -> WSLSessionInformation {
+        WSLSessionInformation {
+            SessionId: 1,
+            UserToken: HANDLE::default(),
+            UserSid: PSID::default(),
+        }
+    }
+
+    #[test]
+    fn test_commands_run_in_order_with_output() {
+        let runner = StartupCommandRunner::new(vec![
+            StartupCommand::new("second", "/bin/echo")
+                .arg("world")
+                .order(2),
+            StartupCommand::new("first", "/bin/echo")
+                .arg("hello")
+                .order(1),
+            StartupCommand::new("distro", "/bin/echo").for_distributions(DistributionRule::All),
+        ]);
+        let report = unsafe { runner.on_vm_started(&api(), &session()) };
+        assert_eq!(report.result, S_OK);
+        let outcomes: Vec<_> = report
+            .outcomes
+            .iter()
+            .map(|o| (o.name.as_str(), o.status.clone(), o.output.as_str()))
+            .collect();
+        assert_eq!(
+            outcomes,
+            [
+                ("first", CommandStatus::Exited(0), "hello\n"),
+                ("second", CommandStatus::Exited(0), "world\n"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_failure_policies() {
+        let runner = StartupCommandRunner::new(vec![
+            StartupCommand::new("ignored", "/bin/false").failure_policy(FailurePolicy::Ignore),
+            StartupCommand::new("slow", "/bin/sleep").timeout(Duration::from_millis(50)),
+            StartupCommand::new("fatal", "/missing").failure_policy(FailurePolicy::Fail),
+            StartupCommand::new("skipped", "/bin/echo"),
+        ]);
+        let report = unsafe { runner.on_vm_started(&api(), &session()) };
+        let statuses: Vec<_> = report.outcomes.iter().map(|o| o.status.clone()).collect();
+        assert_eq!(
+            statuses,
+            [
+                CommandStatus::Exited(1),
+                CommandStatus::TimedOut,
+                CommandStatus::LaunchFailed(E_ACCESSDENIED),
+            ]
+        );
+        assert_eq!(report.result, E_ACCESSDENIED);
+        let errors = ERRORS.take();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("fatal"));
+    }
+
+    #[test]
+    fn test_background_processes_do_not_delay_the_status() {
+        let runner =
+            StartupCommandRunner::new(vec![
+                StartupCommand::new("daemon", "/usr/sbin/daemon").timeout(Duration::from_secs(30))
+            ]);
+        let started = Instant::now();
+        let report = unsafe { runner.on_vm_started(&api(), &session()) };
+        assert!(started.elapsed() < Duration::from_secs(10));
+        assert_eq!(report.result, S_OK);
+        assert_eq!(report.outcomes[0].status, CommandStatus::Exited(0));
+        assert_eq!(report.outcomes[0].output, "started\n");
+    }
+
+    #[test]
+    fn test_exit_status_line() {
+        let line = |status: &str| format!("\n{EXIT_STATUS_MARKER} {status}\n").into_bytes();
+        let mut output = b"out\n".to_vec();
+        output.extend(line("3"));
+        assert_eq!(exit_status(&output, 0), Some((4, CommandStatus::Exited(3))));
+        assert_eq!(exit_status(&output, 5), None);
+        // Incomplete or malformed lines are not a status.
+        assert_eq!(exit_status(&output[..output.len() - 1], 0), None);
+        let mut output = line("none");
+        output.extend(line("timeout"));
+        let position = line("none").len();
+        assert_eq!(
+            exit_status(&output, 0),
+            Some((position, CommandStatus::TimedOut))
+        );
+    }
+
+    #[cfg(wsl_plugin_api_ge_2_4_4)]
+    #[test]
+    fn test_distribution_commands() {
+        use crate::strings::to_wide_string;
+        use crate::windows::core::GUID;
+
+        let runner = StartupCommandRunner::new(vec![
+            StartupCommand::new("ubuntu", "/bin/echo")
+                .arg("ubuntu")
+                .for_distributions("flavor = ubuntu".parse().unwrap()),
+            StartupCommand::new("debian", "/bin/echo")
+                .arg("debian")
+                .for_distributions("flavor = debian".parse().unwrap()),
+        ]);
+        let name = to_wide_string("Ubuntu");
+        let flavor = to_wide_string("ubuntu");
+        let distribution = WSLDistributionInformation {
+            Id: GUID::zeroed(),
+            Name: PCWSTR(name.as_ptr()),
+            PidNamespace: 0,
+            PackageFamilyName: PCWSTR::null(),
+            InitPid: 1,
+            Flavor: PCWSTR(flavor.as_ptr()),
+            Version: PCWSTR::null(),
+        };
+        let report = unsafe { runner.on_distribution_started(&api(), &session(), &distribution) };
+        assert_eq!(report.outcomes.len(), 1);
+        assert_eq!(report.outcomes[0].output, "ubuntu\n");
+        assert_eq!(LAUNCHED.take(), [vec!["/bin/echo", "ubuntu"]]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wrapper_script() {
+        let run = |arguments: &[&str]| {
+            let output = std::process::Command::new("/bin/sh")
+                .args(["-c", WRAPPER_SCRIPT, "sh"])
+                .args(arguments)
+                .output()
+                .unwrap();
+            String::from_utf8(output.stdout).unwrap()
+        };
+        assert_eq!(
+            run(&["5", "/bin/echo", "hello"]),
+            format!("hello\n\n{EXIT_STATUS_MARKER} 0\n")
+        );
+        let started = Instant::now();
+        assert_eq!(
+            run(&["1", "/bin/sleep", "30"]),
+            format!("\n{EXIT_STATUS_MARKER} timeout\n")
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_wrapper_script_with_background_process() {
+        use std::os::fd::OwnedFd;
+        use std::process::{Command, Stdio};
+
+        let (host, guest) = ProcessStream::pair().unwrap();
+        let mut wrapper = Command::new("/bin/sh")
+            .args(["-c", WRAPPER_SCRIPT, "sh", "10"])
+            .args(["/bin/sh", "-c", "sleep 4 & echo started"])
+            .stdin(Stdio::null())
+            .stdout(Stdio::from(OwnedFd::from(guest)))
+            .spawn()
+            .unwrap();
+        let started = Instant::now();
+        let (status, output) = collect(host, started + Duration::from_secs(15));
+        assert_eq!(status, CommandStatus::Exited(0));
+        assert_eq!(output, "started\n");
+        assert!(started.elapsed() < Duration::from_secs(3));
+        wrapper.wait().unwrap();
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_commands_from_config_file() {
+        #[derive(serde::Deserialize)]
+        struct Config {
+            commands: Vec<StartupCommand>,
+        }
+        let config: Config = toml::from_str(
+            r#"
+            [[commands]]
+            name = "update-certificates"
+            path = "/usr/sbin/update-ca-certificates"
+            timeout = 30
+            failure_policy = "fail"
+            distributions = "flavor = debian || flavor = ubuntu"
+            "#,
+        )
+        .unwrap();
+        let command = &config.commands[0];
+        assert_eq!(command.timeout, Duration::from_secs(30));
+        assert_eq!(command.failure_policy, FailurePolicy::Fail);
+        assert!(command.arguments.is_empty());
+    }
+}
@@ -0,0 +1,31 @@
+#[cfg(any(feature = "distribution", feature = "intercept"))]
+use crate::windows::core::PCWSTR;
+
+#[cfg(any(feature = "distribution", feature = "intercept"))]
+/// Reads a nullable wide string handed out by WSL.
+///
+/// # Safety
+///
+/// `value` must be null or point to a valid null-terminated UTF-16 string.
+pub(crate) unsafe fn read_wide_string(value: PCWSTR) -> Option<String> {
+    if value.is_null() {
+        None
+    } else {
+        Some(String::from_utf16_lossy(value.as_wide()))
+    }
+}
+
+#[cfg(any(
+    feature = "calls",
+    all(
+        test,
+        any(
+            feature = "task-executor",
+            all(feature = "distribution", wsl_plugin_api_ge_2_1_2)
+        )
+    )
+))]
+/// Encodes `value` as a null-terminated UTF-16 buffer suitable for a `LPCWSTR` argument.
+pub(crate) fn to_wide_string(value: &str) -> Vec<u16> {
+    value.encode_utf16().chain(std::iter::once(0)).collect()
+}
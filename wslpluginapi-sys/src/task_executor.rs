@@ -0,0 +1,433 @@
+use crate::panics::panic_message;
+use crate::windows::core::{GUID, HRESULT};
+use crate::windows::Win32::Foundation::S_OK;
+use crate::{
+    DistributionInfo, PluginModule, WSLDistributionInformation, WSLSessionId, WSLSessionInformation,
+};
+use std::fmt;
+use std::io;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Grace period used by [`TaskExecutor::default`].
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Default)]
+struct TokenState {
+    cancelled: bool,
+    /// Set by the executor once the task returned or panicked.
+    finished: bool,
+}
+
+/// Signal telling a background task to return.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    state: Arc<(Mutex<TokenState>, Condvar)>,
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, TokenState> {
+        // The state is two flags, always consistent.
+        self.state.0.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn update(&self, update: impl FnOnce(&mut TokenState)) {
+        update(&mut self.lock());
+        self.state.1.notify_all();
+    }
+
+    pub fn cancel(&self) {
+        self.update(|state| state.cancelled = true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.lock().cancelled
+    }
+
+    /// Sleeps for `timeout` or until cancellation, and returns whether the token is cancelled.
+    ///
+    /// Meant for polling loops: `while !token.wait_timeout(interval) { ... }`.
+    pub fn wait_timeout(&self, timeout: Duration) -> bool {
+        let (guard, _) = self
+            .state
+            .1
+            .wait_timeout_while(self.lock(), timeout, |state| !state.cancelled)
+            .unwrap_or_else(PoisonError::into_inner);
+        guard.cancelled
+    }
+
+    fn finish(&self) {
+        self.update(|state| state.finished = true);
+    }
+
+    /// Waits until the task of the token finished or `deadline` passed, and returns whether it
+    /// finished.
+    fn wait_finished(&self, deadline: Instant) -> bool {
+        let timeout = deadline.saturating_duration_since(Instant::now());
+        let (guard, _) = self
+            .state
+            .1
+            .wait_timeout_while(self.lock(), timeout, |state| !state.finished)
+            .unwrap_or_else(PoisonError::into_inner);
+        guard.finished
+    }
+}
+
+/// What a background task is tied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TaskScope {
+    /// Runs until the VM of the session stops.
+    Session(WSLSessionId),
+    /// Runs until the distribution stops, or the VM of its session.
+    Distribution {
+        session: WSLSessionId,
+        distribution: GUID,
+    },
+}
+
+impl TaskScope {
+    pub fn session_id(&self) -> WSLSessionId {
+        match *self {
+            Self::Session(session) | Self::Distribution { session, .. } => session,
+        }
+    }
+}
+
+struct Task {
+    name: String,
+    scope: TaskScope,
+    token: CancellationToken,
+    /// `None` once the task returned on its own and was joined.
+    handle: Option<JoinHandle<()>>,
+    panicked: bool,
+}
+
+impl Task {
+    /// Joins the thread of a task that finished, and returns whether it panicked.
+    fn join(&mut self) -> bool {
+        if let Some(Err(payload)) = self.handle.take().map(JoinHandle::join) {
+            log::error!(
+                "task {} panicked: {}",
+                self.name,
+                panic_message(payload.as_ref())
+            );
+            self.panicked = true;
+        }
+        self.panicked
+    }
+}
+
+impl fmt::Debug for Task {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Task")
+            .field("name", &self.name)
+            .field("scope", &self.scope)
+            .finish()
+    }
+}
+
+/// Outcome of stopping the tasks of a scope.
+///
+/// Tasks that finished on their own before the stop are reported too.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StopReport {
+    /// Tasks that returned.
+    pub completed: Vec<String>,
+    /// Tasks that panicked.
+    pub panicked: Vec<String>,
+    /// Tasks still running when the grace period ended; they are left detached.
+    pub abandoned: Vec<String>,
+}
+
+/// Runs background work started from hooks, which must return quickly.
+///
+/// Each task runs on its own thread and receives a [`CancellationToken`]. The stop hooks
+/// cancel the tasks of their scope and wait for them, at most for the grace period. Added to
+/// a [`HookMultiplexer`](crate::HookMultiplexer), the executor does so on its own.
+#[derive(Debug)]
+pub struct TaskExecutor {
+    grace_period: Duration,
+    tasks: Mutex<Vec<Task>>,
+}
+
+impl Default for TaskExecutor {
+    fn default() -> Self {
+        Self::new(DEFAULT_GRACE_PERIOD)
+    }
+}
+
+impl TaskExecutor {
+    pub fn new(grace_period: Duration) -> Self {
+        Self {
+            grace_period,
+            tasks: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn grace_period(&self) -> Duration {
+        self.grace_period
+    }
+
+    fn lock_tasks(&self) -> MutexGuard<'_, Vec<Task>> {
+        // Nothing panics while the list is borrowed; tolerate it anyway, this runs in hooks.
+        self.tasks.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Starts `task` on a new thread, tied to `scope`.
+    ///
+    /// The closure must own what it uses, since hook arguments do not outlive the hook.
+    pub fn spawn<F>(&self, scope: TaskScope, name: impl Into<String>, task: F) -> io::Result<()>
+    where
+        F: FnOnce(CancellationToken) + Send + 'static,
+    {
+        let name = name.into();
+        let token = CancellationToken::new();
+        let handle = thread::Builder::new().name(name.clone()).spawn({
+            let token = token.clone();
+            move || {
+                let result = catch_unwind(AssertUnwindSafe(|| task(token.clone())));
+                token.finish();
+                if let Err(payload) = result {
+                    resume_unwind(payload);
+                }
+            }
+        })?;
+        let mut tasks = self.lock_tasks();
+        // Release the threads of the tasks that already returned; they stay listed until their
+        // scope stops, to be reported.
+        for task in tasks.iter_mut() {
+            if task.handle.as_ref().is_some_and(JoinHandle::is_finished) {
+                task.join();
+            }
+        }
+        tasks.push(Task {
+            name,
+            scope,
+            token,
+            handle: Some(handle),
+            panicked: false,
+        });
+        Ok(())
+    }
+
+    /// Starts `task` for a running distribution with a snapshot of its information.
+    ///
+    /// # Safety
+    ///
+    /// Same requirements as [`DistributionInfo::from_distribution_information`].
+    pub unsafe fn spawn_for_distribution<F>(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &WSLDistributionInformation,
+        name: impl Into<String>,
+        task: F,
+    ) -> io::Result<()>
+    where
+        F: FnOnce(DistributionInfo, CancellationToken) + Send + 'static,
+    {
+        let info = DistributionInfo::from_distribution_information(distribution);
+        let scope = TaskScope::Distribution {
+            session: session.SessionId,
+            distribution: info.id,
+        };
+        self.spawn(scope, name, move |token| task(info, token))
+    }
+
+    /// Returns the number of tasks still running.
+    pub fn running(&self) -> usize {
+        self.lock_tasks()
+            .iter()
+            .filter(|task| !task.token.lock().finished)
+            .count()
+    }
+
+    /// Cancels and waits for the tasks of a distribution.
+    pub fn stop_distribution(&self, session: WSLSessionId, distribution: &GUID) -> StopReport {
+        let scope = TaskScope::Distribution {
+            session,
+            distribution: *distribution,
+        };
+        self.stop(|task| task == scope)
+    }
+
+    /// Cancels and waits for every task of a session, including those of its distributions.
+    pub fn stop_session(&self, session: WSLSessionId) -> StopReport {
+        self.stop(|task| task.session_id() == session)
+    }
+
+    fn stop(&self, matches: impl Fn(TaskScope) -> bool) -> StopReport {
+        let stopping: Vec<Task> = {
+            let mut tasks = self.lock_tasks();
+            let (stopping, kept) = tasks.drain(..).partition(|task| matches(task.scope));
+            *tasks = kept;
+            stopping
+        };
+        for task in &stopping {
+            task.token.cancel();
+        }
+        let deadline = Instant::now() + self.grace_period;
+        let mut report = StopReport::default();
+        for mut task in stopping {
+            if !task.token.wait_finished(deadline) {
+                log::warn!(
+                    "task {} did not stop within {:?}",
+                    task.name,
+                    self.grace_period
+                );
+                report.abandoned.push(task.name);
+            } else if task.join() {
+                report.panicked.push(task.name);
+            } else {
+                report.completed.push(task.name);
+            }
+        }
+        report
+    }
+}
+
+impl PluginModule for TaskExecutor {
+    fn name(&self) -> &str {
+        "task executor"
+    }
+
+    fn on_vm_stopping(&self, session: &WSLSessionInformation) -> HRESULT {
+        self.stop_session(session.SessionId);
+        S_OK
+    }
+
+    fn on_distribution_stopping(
+        &self,
+        session: &WSLSessionInformation,
+        distribution: &WSLDistributionInformation,
+    ) -> HRESULT {
+        self.stop_distribution(session.SessionId, &distribution.Id);
+        S_OK
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::strings::to_wide_string;
+    use crate::windows::core::PCWSTR;
+    use std::sync::mpsc;
+
+    fn guid(value: u32) -> GUID {
+        GUID::from_values(value, 0, 0, [0; 8])
+    }
+
+    fn until_cancelled(token: CancellationToken) {
+        while !token.wait_timeout(Duration::from_secs(60)) {}
+    }
+
+    #[test]
+    fn test_stop_distribution() {
+        let executor = TaskExecutor::new(Duration::from_secs(10));
+        let first = TaskScope::Distribution {
+            session: 1,
+            distribution: guid(1),
+        };
+        let second = TaskScope::Distribution {
+            session: 1,
+            distribution: guid(2),
+        };
+        executor.spawn(first, "poll", until_cancelled).unwrap();
+        executor.spawn(second, "refresh", until_cancelled).unwrap();
+        executor
+            .spawn(TaskScope::Session(1), "watch", until_cancelled)
+            .unwrap();
+        let report = executor.stop_distribution(1, &guid(1));
+        assert_eq!(report.completed, ["poll"]);
+        assert_eq!(executor.running(), 2);
+        let mut report = executor.stop_session(1);
+        report.completed.sort();
+        assert_eq!(report.completed, ["refresh", "watch"]);
+        assert_eq!(executor.running(), 0);
+    }
+
+    #[test]
+    fn test_grace_period() {
+        let executor = TaskExecutor::new(Duration::from_millis(50));
+        let (release, released) = mpsc::channel::<()>();
+        executor
+            .spawn(TaskScope::Session(3), "other", until_cancelled)
+            .unwrap();
+        executor
+            .spawn(TaskScope::Session(2), "broken", |_| panic!("broken task"))
+            .unwrap();
+        executor
+            .spawn(TaskScope::Session(2), "stubborn", move |_| {
+                let _ = released.recv();
+            })
+            .unwrap();
+        let start = Instant::now();
+        let report = executor.stop_session(2);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(report.abandoned, ["stubborn"]);
+        assert_eq!(report.panicked, ["broken"]);
+        release.send(()).unwrap();
+        assert_eq!(executor.stop_session(3).completed, ["other"]);
+    }
+
+    #[test]
+    fn test_finished_before_stop() {
+        let executor = TaskExecutor::default();
+        let scope = TaskScope::Session(5);
+        executor.spawn(scope, "done", |_| {}).unwrap();
+        executor
+            .spawn(scope, "broken", |_| panic!("broken task"))
+            .unwrap();
+        while executor.running() > 0 {
+            thread::yield_now();
+        }
+        // Spawning joins the finished tasks without losing them.
+        executor.spawn(scope, "last", until_cancelled).unwrap();
+        let report = executor.stop_session(5);
+        assert_eq!(report.completed, ["done", "last"]);
+        assert_eq!(report.panicked, ["broken"]);
+        assert!(report.abandoned.is_empty());
+    }
+
+    #[test]
+    fn test_stop_hooks() {
+        let executor = TaskExecutor::default();
+        let name = to_wide_string("Ubuntu");
+        let empty = to_wide_string("");
+        let distribution = WSLDistributionInformation {
+            Id: guid(7),
+            Name: PCWSTR(name.as_ptr()),
+            PidNamespace: 0,
+            PackageFamilyName: PCWSTR(empty.as_ptr()),
+            InitPid: 1,
+            #[cfg(wsl_plugin_api_ge_2_4_4)]
+            Flavor: PCWSTR(empty.as_ptr()),
+            #[cfg(wsl_plugin_api_ge_2_4_4)]
+            Version: PCWSTR(empty.as_ptr()),
+        };
+        let session = WSLSessionInformation {
+            SessionId: 4,
+            UserToken: Default::default(),
+            UserSid: Default::default(),
+        };
+        let (sender, received) = mpsc::channel();
+        unsafe {
+            executor.spawn_for_distribution(&session, &distribution, "agent", move |info, token| {
+                sender.send(info.name).unwrap();
+                until_cancelled(token);
+            })
+        }
+        .unwrap();
+        assert_eq!(received.recv().unwrap(), "Ubuntu");
+        assert_eq!(
+            executor.on_distribution_stopping(&session, &distribution),
+            S_OK
+        );
+        assert_eq!(executor.running(), 0);
+    }
+}
@@ -0,0 +1,71 @@
+//! Fixtures shared by the unit tests.
+
+use crate::{WSLPluginAPIV1, WSLVersion};
+#[cfg(any(
+    feature = "startup-commands",
+    all(any(feature = "transfer", feature = "agent"), wsl_plugin_api_ge_2_1_2)
+))]
+use {crate::windows::Win32::Networking::WinSock::SOCKET, crate::ProcessStream};
+
+/// Returns an API table reporting `major.minor.revision` without any function; tests set the
+/// functions they call with struct update syntax.
+pub(crate) fn api_table(major: u32, minor: u32, revision: u32) -> WSLPluginAPIV1 {
+    WSLPluginAPIV1 {
+        Version: WSLVersion {
+            Major: major,
+            Minor: minor,
+            Revision: revision,
+        },
+        MountFolder: None,
+        ExecuteBinary: None,
+        PluginError: None,
+        #[cfg(wsl_plugin_api_ge_2_1_2)]
+        ExecuteBinaryInDistribution: None,
+    }
+}
+
+/// Returns a connected pair of streams, the first one as the `SOCKET` WSL hands to the plugin
+/// and the second one for the fake process.
+#[cfg(any(
+    feature = "startup-commands",
+    all(any(feature = "transfer", feature = "agent"), wsl_plugin_api_ge_2_1_2)
+))]
+pub(crate) fn socket_pair() -> (SOCKET, ProcessStream) {
+    #[cfg(windows)]
+    {
+        use std::os::windows::io::IntoRawSocket;
+        let listener = std::net::TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let host = ProcessStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (guest, _) = listener.accept().unwrap();
+        (SOCKET(host.into_raw_socket() as usize), guest)
+    }
+    #[cfg(unix)]
+    {
+        use std::os::fd::IntoRawFd;
+        let (host, guest) = ProcessStream::pair().unwrap();
+        (SOCKET(host.into_raw_fd() as usize), guest)
+    }
+}
+
+/// Collects the null-terminated argument vector passed to `ExecuteBinary`.
+///
+/// # Safety
+///
+/// `arguments` must point to an array of C strings ending with a null pointer.
+#[cfg(any(
+    feature = "startup-commands",
+    all(feature = "transfer", wsl_plugin_api_ge_2_1_2)
+))]
+pub(crate) unsafe fn argument_vector(arguments: *const crate::windows::core::PCSTR) -> Vec<String> {
+    let mut argv = Vec::new();
+    let mut current = arguments;
+    while !(*current).is_null() {
+        argv.push(
+            std::ffi::CStr::from_ptr((*current).0.cast())
+                .to_string_lossy()
+                .into_owned(),
+        );
+        current = current.add(1);
+    }
+    argv
+}
@@ -0,0 +1,525 @@
+use crate::windows::core::GUID;
+use crate::{execute_binary_in_distribution, ProcessStream, WSLPluginAPIV1, WSLSessionId};
+use sha2::{Digest, Sha256};
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::Shutdown;
+
+/// Receives the file into a temporary file next to the destination and reports its checksum.
+const RECEIVE_SCRIPT: &str = r#"dest=$1
+tmp=$(mktemp "$(dirname -- "$dest")/.wslplugin-transfer.XXXXXX" 2>&1) || { printf 'ERROR mktemp %s\n' "$tmp"; exit 1; }
+if ! out=$(cat > "$tmp" 2>&1); then rm -f -- "$tmp"; printf 'ERROR write %s\n' "$out"; exit 1; fi
+sum=$(sha256sum < "$tmp" 2>&1) || { rm -f -- "$tmp"; printf 'ERROR checksum %s\n' "$sum"; exit 1; }
+size=$(wc -c < "$tmp")
+printf 'OK %s %s %s\n' "${sum%% *}" $size "$tmp""#;
+
+/// Moves the verified temporary file to its destination.
+const COMMIT_SCRIPT: &str = r#"if out=$(chmod -- "$3" "$1" 2>&1 && mv -f -- "$1" "$2" 2>&1); then echo OK; else rm -f -- "$1"; printf 'ERROR commit %s\n' "$out"; fi"#;
+
+/// Removes a temporary file whose checksum did not match.
+const ABORT_SCRIPT: &str = r#"rm -f -- "$1"; echo OK"#;
+
+/// Reports the checksum and size of a file, then sends it.
+const SEND_SCRIPT: &str = r#"f=$1
+[ -f "$f" ] || { printf 'ERROR open %s is not a readable file\n' "$f"; exit 1; }
+sum=$(sha256sum < "$f" 2>&1) || { printf 'ERROR checksum %s\n' "$sum"; exit 1; }
+size=$(wc -c < "$f")
+printf 'OK %s %s\n' "${sum%% *}" $size
+exec cat -- "$f""#;
+
+/// Error returned by the file transfer helpers.
+#[derive(Debug)]
+pub enum TransferError {
+    /// The Linux path is not absolute or contains a NUL character.
+    InvalidPath(String),
+    /// `ExecuteBinaryInDistribution` failed.
+    Launch(crate::windows::core::Error),
+    /// Reading the local data or talking to the distribution failed.
+    Io(io::Error),
+    /// A step failed inside the distribution.
+    Remote { stage: String, message: String },
+    /// The data received does not match what was sent.
+    ChecksumMismatch { expected: String, actual: String },
+    /// The distribution answered something unexpected.
+    Protocol(String),
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidPath(path) => write!(f, "invalid Linux path {path:?}"),
+            Self::Launch(error) => write!(f, "failed to start the transfer: {error}"),
+            Self::Io(error) => write!(f, "transfer I/O error: {error}"),
+            Self::Remote { stage, message } => {
+                write!(f, "{stage} failed in the distribution: {message}")
+            }
+            Self::ChecksumMismatch { expected, actual } => write!(
+                f,
+                "checksum mismatch: expected {expected}, received {actual}"
+            ),
+            Self::Protocol(response) => write!(f, "unexpected response {response:?}"),
+        }
+    }
+}
+
+impl std::error::Error for TransferError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Launch(error) => Some(error),
+            Self::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TransferError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+impl From<crate::windows::core::Error> for TransferError {
+    fn from(error: crate::windows::core::Error) -> Self {
+        Self::Launch(error)
+    }
+}
+
+/// Summary of a successful transfer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TransferSummary {
+    pub size: u64,
+    /// Lowercase hexadecimal SHA-256 of the content.
+    pub sha256: String,
+}
+
+fn validate_path(linux_path: &str) -> Result<(), TransferError> {
+    if linux_path.starts_with('/') && !linux_path.contains('\0') {
+        Ok(())
+    } else {
+        Err(TransferError::InvalidPath(linux_path.to_owned()))
+    }
+}
+
+fn to_hex(digest: &[u8]) -> String {
+    digest.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+unsafe fn run_script(
+    api: &WSLPluginAPIV1,
+    session: WSLSessionId,
+    distribution: &GUID,
+    script: &str,
+    arguments: &[&str],
+) -> Result<ProcessStream, TransferError> {
+    let argv: Vec<&str> = ["/bin/sh", "-c", script, "sh"]
+        .into_iter()
+        .chain(arguments.iter().copied())
+        .collect();
+    Ok(execute_binary_in_distribution(
+        api,
+        session,
+        distribution,
+        argv[0],
+        &argv,
+    )?)
+}
+
+/// Reads the status line of a script: `OK <fields...>` or `ERROR <stage> <message>`.
+fn read_status<R: BufRead>(reader: &mut R) -> Result<String, TransferError> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let line = line.trim_end_matches('\n');
+    if let Some(fields) = line.strip_prefix("OK") {
+        Ok(fields.trim_start().to_owned())
+    } else if let Some(error) = line.strip_prefix("ERROR ") {
+        let (stage, message) = error.split_once(' ').unwrap_or((error, ""));
+        Err(TransferError::Remote {
+            stage: stage.to_owned(),
+            message: message.to_owned(),
+        })
+    } else {
+        Err(TransferError::Protocol(line.to_owned()))
+    }
+}
+
+/// Copies `source` to `linux_path` inside a distribution and sets its permissions to `mode`.
+///
+/// The content is streamed into a temporary file next to the destination, verified with a
+/// SHA-256 checksum, then renamed over the destination, so readers never see a partial file.
+/// The parent directory must exist.
+///
+/// # Safety
+///
+/// `api` must be the table received at the plugin entry point, and WSL must be at least 2.1.2.
+pub unsafe fn copy_into_distribution<R: Read>(
+    api: &WSLPluginAPIV1,
+    session: WSLSessionId,
+    distribution: &GUID,
+    mut source: R,
+    linux_path: &str,
+    mode: u32,
+) -> Result<TransferSummary, TransferError> {
+    validate_path(linux_path)?;
+    let mut stream = run_script(api, session, distribution, RECEIVE_SCRIPT, &[linux_path])?;
+    let mut hasher = Sha256::new();
+    let mut size = 0u64;
+    let mut buffer = vec![0u8; 64 * 1024];
+    let sent = loop {
+        let read = match source.read(&mut buffer) {
+            Ok(0) => break Ok(()),
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if let Err(e) = stream.write_all(&buffer[..read]) {
+            // The distribution may have given up early; its answer explains why.
+            break Err(e);
+        }
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    };
+    let _ = stream.shutdown(Shutdown::Write);
+    let status = read_status(&mut BufReader::new(&mut stream));
+    let fields = match (status, sent) {
+        (Err(error @ TransferError::Remote { .. }), _) => return Err(error),
+        (_, Err(e)) => return Err(e.into()),
+        (status, Ok(())) => status?,
+    };
+    let mut parts = fields.splitn(3, ' ');
+    let (Some(actual), Some(remote_size), Some(temporary)) =
+        (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(TransferError::Protocol(fields));
+    };
+    let expected = to_hex(&hasher.finalize());
+    if actual != expected || remote_size.parse::<u64>().ok() != Some(size) {
+        let mut abort = run_script(api, session, distribution, ABORT_SCRIPT, &[temporary])?;
+        let _ = read_status(&mut BufReader::new(&mut abort));
+        return Err(TransferError::ChecksumMismatch {
+            expected,
+            actual: actual.to_owned(),
+        });
+    }
+    let mode = format!("{mode:o}");
+    let mut commit = run_script(
+        api,
+        session,
+        distribution,
+        COMMIT_SCRIPT,
+        &[temporary, linux_path, &mode],
+    )?;
+    read_status(&mut BufReader::new(&mut commit))?;
+    Ok(TransferSummary {
+        size,
+        sha256: expected,
+    })
+}
+
+/// Streams the content of `linux_path` inside a distribution into `destination`.
+///
+/// The checksum is verified once everything has been written, so `destination` may have
+/// received data when a [`TransferError::ChecksumMismatch`] is returned.
+///
+/// # Safety
+///
+/// `api` must be the table received at the plugin entry point, and WSL must be at least 2.1.2.
+pub unsafe fn read_from_distribution_into<W: Write>(
+    api: &WSLPluginAPIV1,
+    session: WSLSessionId,
+    distribution: &GUID,
+    linux_path: &str,
+    mut destination: W,
+) -> Result<TransferSummary, TransferError> {
+    validate_path(linux_path)?;
+    let stream = run_script(api, session, distribution, SEND_SCRIPT, &[linux_path])?;
+    let _ = stream.shutdown(Shutdown::Write);
+    let mut reader = BufReader::new(stream);
+    let fields = read_status(&mut reader)?;
+    let (Some(expected), Some(Ok(size))) = ({
+        let mut fields = fields.split(' ');
+        (fields.next(), fields.next().map(str::parse::<u64>))
+    }) else {
+        return Err(TransferError::Protocol(fields));
+    };
+    let mut hasher = Sha256::new();
+    let mut received = 0u64;
+    let mut buffer = vec![0u8; 64 * 1024];
+    loop {
+        let read = match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        hasher.update(&buffer[..read]);
+        destination.write_all(&buffer[..read])?;
+        received += read as u64;
+    }
+    let actual = to_hex(&hasher.finalize());
+    if actual != expected || received != size {
+        return Err(TransferError::ChecksumMismatch {
+            expected: expected.to_owned(),
+            actual,
+        });
+    }
+    Ok(TransferSummary {
+        size,
+        sha256: actual,
+    })
+}
+
+/// Reads the whole content of `linux_path` inside a distribution.
+///
+/// # Safety
+///
+/// See [`read_from_distribution_into`].
+pub unsafe fn read_from_distribution(
+    api: &WSLPluginAPIV1,
+    session: WSLSessionId,
+    distribution: &GUID,
+    linux_path: &str,
+) -> Result<Vec<u8>, TransferError> {
+    let mut content = Vec::new();
+    read_from_distribution_into(api, session, distribution, linux_path, &mut content)?;
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{api_table, argument_vector, socket_pair};
+    use crate::windows::core::{HRESULT, PCSTR};
+    use crate::windows::Win32::Foundation::S_OK;
+    use crate::windows::Win32::Networking::WinSock::SOCKET;
+    use std::collections::BTreeMap;
+    use std::sync::Mutex;
+
+    /// The only directory of the fake distribution.
+    const FAKE_DIR: &str = "/srv";
+
+    /// Files of the fake distribution, with their content and mode.
+    static FAKE_FILES: Mutex<BTreeMap<String, (Vec<u8>, String)>> = Mutex::new(BTreeMap::new());
+
+    fn fake_file(path: &str) -> Option<(Vec<u8>, String)> {
+        FAKE_FILES.lock().unwrap().get(path).cloned()
+    }
+
+    /// Answers the transfer scripts the way they behave in a distribution, without a shell.
+    ///
+    /// Content sent to or read from a path containing `corrupt` is altered on the way.
+    fn run_fake_script(script: &str, arguments: &[String], mut stream: ProcessStream) {
+        let files = || FAKE_FILES.lock().unwrap();
+        let response = match script {
+            RECEIVE_SCRIPT => {
+                let destination = &arguments[0];
+                let (parent, name) = destination.rsplit_once('/').unwrap();
+                let mut content = Vec::new();
+                stream.read_to_end(&mut content).unwrap();
+                if parent != FAKE_DIR {
+                    format!("ERROR mktemp {parent}: No such file or directory\n")
+                } else {
+                    if destination.contains("corrupt") {
+                        content[0] ^= 1;
+                    }
+                    let sha256 = to_hex(&Sha256::digest(&content));
+                    let size = content.len();
+                    let temporary = format!("{parent}/.wslplugin-transfer.{name}");
+                    files().insert(temporary.clone(), (content, "600".to_owned()));
+                    format!("OK {sha256} {size} {temporary}\n")
+                }
+            }
+            COMMIT_SCRIPT => {
+                let (content, _) = files().remove(&arguments[0]).unwrap();
+                files().insert(arguments[1].clone(), (content, arguments[2].clone()));
+                "OK\n".to_owned()
+            }
+            ABORT_SCRIPT => {
+                files().remove(&arguments[0]);
+                "OK\n".to_owned()
+            }
+            SEND_SCRIPT => match fake_file(&arguments[0]) {
+                Some((mut content, _)) => {
+                    let header = format!(
+                        "OK {} {}\n",
+                        to_hex(&Sha256::digest(&content)),
+                        content.len()
+                    );
+                    if arguments[0].contains("corrupt") {
+                        content[0] ^= 1;
+                    }
+                    stream.write_all(header.as_bytes()).unwrap();
+                    stream.write_all(&content).unwrap();
+                    return;
+                }
+                None => format!("ERROR open {} is not a readable file\n", arguments[0]),
+            },
+            _ => panic!("unexpected script {script:?}"),
+        };
+        stream.write_all(response.as_bytes()).unwrap();
+    }
+
+    unsafe extern "C" fn fake_execute_binary_in_distribution(
+        _session: WSLSessionId,
+        _distribution: *const GUID,
+        _path: PCSTR,
+        arguments: *mut PCSTR,
+        socket: *mut SOCKET,
+    ) -> HRESULT {
+        // `/bin/sh -c <script> sh <arguments...>`
+        let argv = argument_vector(arguments);
+        let (host, guest) = socket_pair();
+        std::thread::spawn(move || run_fake_script(&argv[2], &argv[4..], guest));
+        *socket = host;
+        S_OK
+    }
+
+    fn fake_api() -> WSLPluginAPIV1 {
+        WSLPluginAPIV1 {
+            ExecuteBinaryInDistribution: Some(fake_execute_binary_in_distribution),
+            ..api_table(2, 4, 4)
+        }
+    }
+
+    #[test]
+    fn test_fake_checksum_mismatch() {
+        let api = fake_api();
+        let guid = GUID::zeroed();
+        let error = unsafe {
+            copy_into_distribution(&api, 1, &guid, &b"data"[..], "/srv/corrupt.conf", 0o644)
+        }
+        .unwrap_err();
+        assert!(matches!(error, TransferError::ChecksumMismatch { .. }));
+        // The temporary file is removed and the destination left untouched.
+        assert_eq!(fake_file("/srv/.wslplugin-transfer.corrupt.conf"), None);
+        assert_eq!(fake_file("/srv/corrupt.conf"), None);
+
+        FAKE_FILES.lock().unwrap().insert(
+            "/srv/corrupt.log".to_owned(),
+            (b"log".to_vec(), "644".to_owned()),
+        );
+        let error =
+            unsafe { read_from_distribution(&api, 1, &guid, "/srv/corrupt.log") }.unwrap_err();
+        assert!(matches!(error, TransferError::ChecksumMismatch { .. }));
+    }
+
+    /// Runs the real scripts with the host shell.
+    #[cfg(unix)]
+    mod shell {
+        use super::*;
+        use crate::windows::Win32::Foundation::E_FAIL;
+        use std::cell::RefCell;
+        use std::ffi::CStr;
+        use std::os::fd::{IntoRawFd, OwnedFd};
+        use std::os::unix::fs::PermissionsExt;
+        use std::os::unix::net::UnixStream;
+        use std::path::PathBuf;
+        use std::process::{Child, Command, Stdio};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        thread_local! {
+            /// Processes started by the test running on this thread, reaped by
+            /// [`wait_children`].
+            static CHILDREN: RefCell<Vec<Child>> = const { RefCell::new(Vec::new()) };
+        }
+
+        fn wait_children() {
+            for mut child in CHILDREN.take() {
+                child.wait().unwrap();
+            }
+        }
+
+        /// Runs the requested binary on the test host, connected to the returned socket.
+        unsafe extern "C" fn local_execute_binary_in_distribution(
+            _session: WSLSessionId,
+            _distribution: *const GUID,
+            path: PCSTR,
+            arguments: *mut PCSTR,
+            socket: *mut SOCKET,
+        ) -> HRESULT {
+            let argv = argument_vector(arguments);
+            let (host, guest) = UnixStream::pair().unwrap();
+            let guest: OwnedFd = guest.into();
+            let spawned = Command::new(CStr::from_ptr(path.0.cast()).to_str().unwrap())
+                .args(&argv[1..])
+                .stdin(Stdio::from(guest.try_clone().unwrap()))
+                .stdout(Stdio::from(guest))
+                .spawn();
+            match spawned {
+                Ok(child) => {
+                    CHILDREN.with_borrow_mut(|children| children.push(child));
+                    *socket = SOCKET(host.into_raw_fd() as usize);
+                    S_OK
+                }
+                Err(_) => E_FAIL,
+            }
+        }
+
+        fn api() -> WSLPluginAPIV1 {
+            WSLPluginAPIV1 {
+                ExecuteBinaryInDistribution: Some(local_execute_binary_in_distribution),
+                ..api_table(2, 4, 4)
+            }
+        }
+
+        fn scratch_dir() -> PathBuf {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let dir = std::env::temp_dir().join(format!(
+                "wslpluginapi-transfer-{}-{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed)
+            ));
+            std::fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        #[test]
+        fn test_round_trip() {
+            let dir = scratch_dir();
+            let path = dir.join("config.toml");
+            let path = path.to_str().unwrap();
+            let content: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+            let api = api();
+            let guid = GUID::zeroed();
+            let summary =
+                unsafe { copy_into_distribution(&api, 1, &guid, content.as_slice(), path, 0o640) }
+                    .unwrap();
+            assert_eq!(summary.size, content.len() as u64);
+            assert_eq!(summary.sha256, to_hex(&Sha256::digest(&content)));
+            let mode = std::fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+            assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 1);
+            let read_back = unsafe { read_from_distribution(&api, 1, &guid, path) }.unwrap();
+            assert_eq!(read_back, content);
+            wait_children();
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+
+        #[test]
+        fn test_remote_errors() {
+            let api = api();
+            let guid = GUID::zeroed();
+            let dir = scratch_dir();
+            let missing_dir = dir.join("missing").join("file");
+            let error = unsafe {
+                copy_into_distribution(
+                    &api,
+                    1,
+                    &guid,
+                    &b"data"[..],
+                    missing_dir.to_str().unwrap(),
+                    0o644,
+                )
+            }
+            .unwrap_err();
+            assert!(matches!(error, TransferError::Remote { ref stage, .. } if stage == "mktemp"));
+            let error =
+                unsafe { read_from_distribution(&api, 1, &guid, missing_dir.to_str().unwrap()) }
+                    .unwrap_err();
+            assert!(matches!(error, TransferError::Remote { ref stage, .. } if stage == "open"));
+            let error = unsafe { read_from_distribution(&api, 1, &guid, "relative") }.unwrap_err();
+            assert!(matches!(error, TransferError::InvalidPath(_)));
+            wait_children();
+            std::fs::remove_dir_all(dir).unwrap();
+        }
+    }
+}
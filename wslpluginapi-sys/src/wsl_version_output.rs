@@ -0,0 +1,293 @@
+use crate::windows::core::HRESULT;
+use crate::windows::Win32::Foundation::S_OK;
+use crate::{WSLVersion, WSL_E_PLUGIN_REQUIRES_UPDATE};
+use std::fmt;
+use std::str::FromStr;
+
+/// Versions reported by `wsl.exe --version`.
+///
+/// Only the WSL version is required; the other components are kept as printed since their
+/// formats vary (`5.15.167.4-1`, `10.0.26100.1-240331-1435.ge-release`, ...).
+#[derive(Debug, Clone)]
+pub struct WslVersionOutput {
+    /// The WSL package version, without its fourth component.
+    pub wsl: WSLVersion,
+    /// The WSL package version as printed, such as `2.4.4.0`.
+    pub wsl_text: String,
+    pub kernel: Option<String>,
+    pub wslg: Option<String>,
+    pub msrdc: Option<String>,
+    pub direct3d: Option<String>,
+    pub dxcore: Option<String>,
+    pub windows: Option<String>,
+}
+
+/// Error returned when the output of `wsl.exe --version` cannot be understood.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWslVersionOutputError {
+    /// No line holds the WSL version.
+    MissingWslVersion,
+    /// The WSL version is not of the form `major.minor.revision`.
+    InvalidWslVersion(String),
+}
+
+impl fmt::Display for ParseWslVersionOutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingWslVersion => f.write_str("no WSL version in the output"),
+            Self::InvalidWslVersion(version) => write!(f, "invalid WSL version {version:?}"),
+        }
+    }
+}
+
+impl std::error::Error for ParseWslVersionOutputError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Component {
+    Wsl,
+    Kernel,
+    Wslg,
+    Msrdc,
+    Direct3D,
+    DxCore,
+    Windows,
+}
+
+/// Order in which `wsl.exe` prints the components, whatever the display language.
+const COMPONENT_ORDER: [Component; 7] = [
+    Component::Wsl,
+    Component::Kernel,
+    Component::Wslg,
+    Component::Msrdc,
+    Component::Direct3D,
+    Component::DxCore,
+    Component::Windows,
+];
+
+impl Component {
+    /// Recognizes the component from the product names that no translation changes.
+    fn from_label(label: &str) -> Option<Self> {
+        let label = label.to_ascii_lowercase();
+        if label.contains("wslg") {
+            Some(Self::Wslg)
+        } else if label.contains("wsl") {
+            Some(Self::Wsl)
+        } else if label.contains("msrdc") {
+            Some(Self::Msrdc)
+        } else if label.contains("direct3d") {
+            Some(Self::Direct3D)
+        } else if label.contains("dxcore") {
+            Some(Self::DxCore)
+        } else if label.contains("windows") {
+            Some(Self::Windows)
+        } else if label.contains("kernel") {
+            Some(Self::Kernel)
+        } else {
+            None
+        }
+    }
+}
+
+fn parse_wsl_version(text: &str) -> Result<WSLVersion, ParseWslVersionOutputError> {
+    let error = || ParseWslVersionOutputError::InvalidWslVersion(text.to_owned());
+    let mut components = text.split('.').map(|component| component.parse::<u32>());
+    let mut next = || components.next().ok_or_else(error)?.map_err(|_| error());
+    Ok(WSLVersion {
+        Major: next()?,
+        Minor: next()?,
+        Revision: next()?,
+    })
+}
+
+/// Decodes the raw output of `wsl.exe`, which is UTF-16LE when redirected, or UTF-8.
+// `usize::is_multiple_of` needs Rust 1.87, which the workspace does not require.
+#[allow(clippy::manual_is_multiple_of)]
+pub fn decode_wsl_output(bytes: &[u8]) -> String {
+    let utf16 = if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        Some(rest)
+    } else if bytes.len() % 2 == 0 && bytes.len() >= 2 && bytes[0] != 0 && bytes[1] == 0 {
+        // Text starting with a Latin character is unambiguous in UTF-16LE.
+        Some(bytes)
+    } else {
+        None
+    };
+    match utf16 {
+        Some(bytes) => {
+            let units: Vec<u16> = bytes
+                .chunks_exact(2)
+                .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        None => {
+            let bytes = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]).unwrap_or(bytes);
+            String::from_utf8_lossy(bytes).into_owned()
+        }
+    }
+}
+
+impl WslVersionOutput {
+    /// Parses the raw bytes written by `wsl.exe --version`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, ParseWslVersionOutputError> {
+        decode_wsl_output(bytes).parse()
+    }
+
+    /// Applies the check of [`require_version`] to the installed WSL version.
+    ///
+    /// Returns `WSL_E_PLUGIN_REQUIRES_UPDATE` when a plugin requiring this version would be
+    /// rejected by the installed WSL.
+    ///
+    /// [`require_version`]: crate::require_version
+    pub fn require_version(
+        &self,
+        required_major: u32,
+        required_minor: u32,
+        required_revision: u32,
+    ) -> HRESULT {
+        let required = WSLVersion {
+            Major: required_major,
+            Minor: required_minor,
+            Revision: required_revision,
+        };
+        if self.wsl < required {
+            WSL_E_PLUGIN_REQUIRES_UPDATE
+        } else {
+            S_OK
+        }
+    }
+}
+
+impl FromStr for WslVersionOutput {
+    type Err = ParseWslVersionOutputError;
+
+    /// Parses the `label: value` lines of `wsl.exe --version`.
+    ///
+    /// Labels are translated, so each line is identified by the product name it contains, or
+    /// failing that by its position.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut values: [Option<String>; COMPONENT_ORDER.len()] = Default::default();
+        let lines = s.lines().filter_map(|line| {
+            let (label, value) = line.split_once([':', '：'])?;
+            let value = value.trim();
+            value
+                .starts_with(|c: char| c.is_ascii_digit())
+                .then(|| (label.trim(), value))
+        });
+        for (position, (label, value)) in lines.enumerate() {
+            let component =
+                Component::from_label(label).or_else(|| COMPONENT_ORDER.get(position).copied());
+            let Some(component) = component else {
+                continue;
+            };
+            let index = COMPONENT_ORDER
+                .iter()
+                .position(|known| *known == component)
+                .unwrap();
+            values[index].get_or_insert_with(|| value.to_owned());
+        }
+        let [wsl, kernel, wslg, msrdc, direct3d, dxcore, windows] = values;
+        let wsl_text = wsl.ok_or(ParseWslVersionOutputError::MissingWslVersion)?;
+        Ok(Self {
+            wsl: parse_wsl_version(&wsl_text)?,
+            wsl_text,
+            kernel,
+            wslg,
+            msrdc,
+            direct3d,
+            dxcore,
+            windows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_version(output: &WslVersionOutput, major: u32, minor: u32, revision: u32) {
+        assert_eq!(
+            (output.wsl.Major, output.wsl.Minor, output.wsl.Revision),
+            (major, minor, revision)
+        );
+    }
+
+    #[test]
+    fn test_english_output() {
+        let output: WslVersionOutput = include_str!("../fixtures/wsl-version/en-us.txt")
+            .parse()
+            .unwrap();
+        assert_version(&output, 2, 4, 4);
+        assert_eq!(output.wsl_text, "2.4.4.0");
+        assert_eq!(output.kernel.as_deref(), Some("5.15.167.4-1"));
+        assert_eq!(output.wslg.as_deref(), Some("1.0.65"));
+        assert_eq!(output.msrdc.as_deref(), Some("1.2.5620"));
+        assert_eq!(output.direct3d.as_deref(), Some("1.611.1-81528511"));
+        assert_eq!(
+            output.dxcore.as_deref(),
+            Some("10.0.26100.1-240331-1435.ge-release")
+        );
+        assert_eq!(output.windows.as_deref(), Some("10.0.22631.4460"));
+    }
+
+    #[test]
+    fn test_localized_output() {
+        let french: WslVersionOutput = include_str!("../fixtures/wsl-version/fr-fr.txt")
+            .parse()
+            .unwrap();
+        assert_version(&french, 2, 4, 4);
+        assert_eq!(french.kernel.as_deref(), Some("5.15.167.4-1"));
+        let german: WslVersionOutput = include_str!("../fixtures/wsl-version/de-de.txt")
+            .parse()
+            .unwrap();
+        assert_version(&german, 2, 3, 26);
+        assert_eq!(german.windows.as_deref(), Some("10.0.19045.5131"));
+        // The kernel label has no product name and is found by its position.
+        let chinese: WslVersionOutput = include_str!("../fixtures/wsl-version/zh-cn.txt")
+            .parse()
+            .unwrap();
+        assert_version(&chinese, 2, 1, 5);
+        assert_eq!(chinese.kernel.as_deref(), Some("5.15.146.1-2"));
+        assert_eq!(chinese.wslg.as_deref(), Some("1.0.60"));
+    }
+
+    #[test]
+    fn test_utf16_output() {
+        let output = WslVersionOutput::from_bytes(include_bytes!(
+            "../fixtures/wsl-version/en-us-utf16le.txt"
+        ))
+        .unwrap();
+        assert_version(&output, 2, 4, 4);
+        assert_eq!(output.windows.as_deref(), Some("10.0.22631.4460"));
+        let mut with_bom = vec![0xFF, 0xFE];
+        with_bom.extend(
+            "WSL version: 2.0.9.0\r\n"
+                .encode_utf16()
+                .flat_map(u16::to_le_bytes),
+        );
+        assert_version(&WslVersionOutput::from_bytes(&with_bom).unwrap(), 2, 0, 9);
+    }
+
+    #[test]
+    fn test_require_version() {
+        let output: WslVersionOutput = "WSL version: 2.1.5.0".parse().unwrap();
+        assert_eq!(output.require_version(2, 1, 5), S_OK);
+        assert_eq!(
+            output.require_version(2, 4, 4),
+            WSL_E_PLUGIN_REQUIRES_UPDATE
+        );
+    }
+
+    #[test]
+    fn test_invalid_output() {
+        assert_eq!(
+            "Usage: wsl.exe [Argument]"
+                .parse::<WslVersionOutput>()
+                .unwrap_err(),
+            ParseWslVersionOutputError::MissingWslVersion
+        );
+        assert!(matches!(
+            "WSL version: 2.x".parse::<WslVersionOutput>(),
+            Err(ParseWslVersionOutputError::InvalidWslVersion(_))
+        ));
+    }
+}
@@ -80,24 +80,86 @@ fn fetch_cargo_metadata() -> Result<cargo_metadata::Metadata> {
     Ok(metadata)
 }
 
+/// Key of the package metadata table listing the vendored `Microsoft.WSL.PluginApi` versions.
+const API_VERSIONS_METADATA_KEY: &str = "wsl-plugin-api";
+
+/// Reads the `Microsoft.WSL.PluginApi` versions a package vendors from
+/// `[package.metadata.wsl-plugin-api] versions = [...]`.
+fn nuget_package_versions(package: &cargo_metadata::Package) -> Result<Vec<String>> {
+    let Some(versions) = package.metadata[API_VERSIONS_METADATA_KEY].get("versions") else {
+        return Ok(Vec::new());
+    };
+    versions
+        .as_array()
+        .and_then(|versions| {
+            versions
+                .iter()
+                .map(|version| version.as_str().map(str::to_owned))
+                .collect()
+        })
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "package.metadata.{}.versions of {} must be an array of strings",
+                API_VERSIONS_METADATA_KEY,
+                package.name
+            )
+        })
+}
+
 fn process_package(
     package: &cargo_metadata::Package,
     workspace_root: &Path,
 ) -> Result<ThirdPartyNoticePackage> {
     debug!("Processing package: {}", package.name);
 
-    let version = &package.version;
-    let nuget_package_version = &version.build;
+    let nuget_package_versions = nuget_package_versions(package)?;
     debug!(
-        "Package '{}' build metadata version: {:?}",
-        package.name, nuget_package_version
+        "Package '{}' NuGet package versions: {:?}",
+        package.name, nuget_package_versions
     );
-    if nuget_package_version.is_empty() {
-        warn!("No version found for package: {}", package.name);
-        return Ok(ThirdPartyNoticePackage::new(package.name.to_string()));
+    let mut notice = ThirdPartyNoticePackage::new(package.name.to_string());
+    if nuget_package_versions.is_empty() {
+        info!(
+            "No NuGet package version listed for package: {}",
+            package.name
+        );
+        return Ok(notice);
     }
 
     let nuget_package_name = "Microsoft.WSL.PluginApi";
+    let third_party_dir = package.manifest_path.parent().unwrap().join("third_party");
+    let third_party_wsl_nuget_dir = third_party_dir.join(nuget_package_name);
+    prepare_third_party_dirs(
+        &third_party_dir.as_std_path(),
+        &third_party_wsl_nuget_dir.as_std_path(),
+    )?;
+    for nuget_package_version in &nuget_package_versions {
+        // Each version is vendored side by side so the build script can pick one.
+        let version_dir = third_party_wsl_nuget_dir.join(nuget_package_version);
+        fs::create_dir_all(&version_dir)?;
+        notice.push(vendor_nuget_package(
+            nuget_package_name,
+            nuget_package_version,
+            workspace_root,
+            version_dir.as_std_path(),
+        )?);
+    }
+    notice.generate_notice(
+        &package
+            .manifest_path
+            .parent()
+            .unwrap()
+            .join("THIRD-PARTY-NOTICES.md"),
+    )?;
+    Ok(notice)
+}
+
+fn vendor_nuget_package(
+    nuget_package_name: &str,
+    nuget_package_version: &str,
+    workspace_root: &Path,
+    version_dir: &Path,
+) -> Result<ThirdPartyNoticeItem> {
     debug!(
         "Ensuring NuGet package installed: {} @ {}",
         nuget_package_name, nuget_package_version
@@ -105,24 +167,14 @@ fn process_package(
 
     let nuget_pkg_path = ensure_package_installed(
         nuget_package_name,
-        nuget_package_version.as_str(),
+        nuget_package_version,
         workspace_root,
         Mode::TryNuget,
     )?;
     debug!("NuGet package path: {}", nuget_pkg_path.display());
 
-    let third_party_dir = package.manifest_path.parent().unwrap().join("third_party");
-    let third_party_wsl_nuget_dir = third_party_dir.join(nuget_package_name);
-    prepare_third_party_dirs(
-        &third_party_dir.as_std_path(),
-        &third_party_wsl_nuget_dir.as_std_path(),
-    )?;
-    let nuspec_data = get_nuspec_from_nupkg(
-        &nuget_pkg_path,
-        nuget_package_name,
-        nuget_package_version.as_str(),
-    )?
-    .unwrap();
+    let nuspec_data =
+        get_nuspec_from_nupkg(&nuget_pkg_path, nuget_package_name, nuget_package_version)?.unwrap();
     let licence: Option<LicenceContent> = nuspec_data.metadata.get_licence_content()?;
     let mut notice_item = ThirdPartyNoticeItem::new(
         nuget_package_name.into(),
@@ -134,30 +186,14 @@ fn process_package(
         nuspec_data.metadata.copyright.clone(),
         licence,
     );
-    let headers = copy_native_headers(&nuget_pkg_path, &third_party_wsl_nuget_dir.as_std_path())?;
+    let headers = copy_native_headers(&nuget_pkg_path, version_dir)?;
     notice_item.files_mut().extend(headers);
-    let readme: Option<DistributedFile> = handle_readme(
-        &nuspec_data,
-        nuget_pkg_path.as_ref(),
-        third_party_wsl_nuget_dir.as_ref(),
-    )?;
+    let readme: Option<DistributedFile> =
+        handle_readme(&nuspec_data, nuget_pkg_path.as_ref(), version_dir)?;
     notice_item.files_mut().extend(readme.into_iter());
-    let licenses = handle_license(
-        &nuspec_data,
-        nuget_pkg_path.as_ref(),
-        third_party_wsl_nuget_dir.as_ref(),
-    )?;
+    let licenses = handle_license(&nuspec_data, nuget_pkg_path.as_ref(), version_dir)?;
     notice_item.files_mut().extend(licenses.into_iter());
-    let mut notice = ThirdPartyNoticePackage::new(package.name.to_string());
-    notice.push(notice_item);
-    notice.generate_notice(
-        &package
-            .manifest_path
-            .parent()
-            .unwrap()
-            .join("THIRD-PARTY-NOTICES.md"),
-    )?;
-    Ok(notice)
+    Ok(notice_item)
 }
 
 fn prepare_third_party_dirs(